@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use url::Url;
+
+use crate::events::DownloadEvent;
+use crate::{enqueue_download_internal, AppState, EnqueueOptions};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Opt-in watcher that picks download links out of copied text.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ClipboardSettings {
+    pub enabled: bool,
+    /// Enqueue matching links right away instead of offering them through a
+    /// `link_detected` event.
+    pub auto_enqueue: bool,
+    /// File extensions (without the dot) that mark a link as a download.
+    pub extensions: Vec<String>,
+    /// Hosts whose links are always taken; subdomains match too.
+    pub hosts: Vec<String>,
+}
+
+impl Default for ClipboardSettings {
+    fn default() -> Self {
+        let extensions = [
+            "zip", "rar", "7z", "tar", "gz", "xz", "iso", "exe", "msi", "dmg", "deb", "rpm",
+            "apk", "mp4", "mkv", "mp3", "flac", "pdf", "torrent",
+        ];
+        ClipboardSettings {
+            enabled: false,
+            auto_enqueue: false,
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            hosts: Vec::new(),
+        }
+    }
+}
+
+impl ClipboardSettings {
+    /// Whether `url` is an http(s) link that matches an extension or host rule.
+    pub fn matches(&self, url: &Url) -> bool {
+        if !matches!(url.scheme(), "http" | "https") {
+            return false;
+        }
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let host_match = self.hosts.iter().any(|h| {
+            let h = h.trim().trim_start_matches('.').to_ascii_lowercase();
+            !h.is_empty() && (host == h || host.ends_with(&format!(".{}", h)))
+        });
+        let path = url.path().to_ascii_lowercase();
+        let extension_match = self.extensions.iter().any(|ext| {
+            let ext = ext.trim().trim_start_matches('.').to_ascii_lowercase();
+            !ext.is_empty() && path.ends_with(&format!(".{}", ext))
+        });
+        host_match || extension_match
+    }
+}
+
+/// Every http(s) URL in `text`, one per whitespace separated token, with
+/// surrounding quotes and brackets trimmed.
+fn links_in(text: &str) -> Vec<Url> {
+    text.split_whitespace()
+        .map(|token| token.trim_matches(|c: char| "\"'<>()[]{},;".contains(c)))
+        .filter(|token| token.starts_with("http://") || token.starts_with("https://"))
+        .filter_map(|token| Url::parse(token).ok())
+        .collect()
+}
+
+/// Whether a download for `url` already exists, under its own URL, a mirror
+/// or the URL it was redirected to.
+async fn already_known(state: &AppState, url: &str) -> bool {
+    state.downloads.lock().await.iter().any(|d| {
+        d.url == url || d.mirrors.iter().any(|m| m == url) || d.effective_url.as_deref() == Some(url)
+    })
+}
+
+async fn read_clipboard(app_handle: &AppHandle) -> Option<String> {
+    let app_handle = app_handle.clone();
+    tokio::task::spawn_blocking(move || app_handle.clipboard().read_text().ok())
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Polls the clipboard while `ClipboardSettings::enabled` is on. Whatever is
+/// on the clipboard when watching starts is ignored, and each link is only
+/// handled once per session.
+pub fn spawn_clipboard_watcher(app_handle: AppHandle) {
+    let state = app_handle.state::<AppState>().inner().clone();
+    tokio::spawn(async move {
+        let mut last: Option<String> = None;
+        let mut seen = HashSet::new();
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let settings = state.clipboard.lock().await.clone();
+            if !settings.enabled {
+                last = None;
+                continue;
+            }
+
+            let Some(text) = read_clipboard(&app_handle).await else {
+                continue;
+            };
+            if last.as_deref() == Some(text.as_str()) {
+                continue;
+            }
+            let first_read = last.is_none();
+            last = Some(text.clone());
+            if first_read {
+                continue;
+            }
+
+            for url in links_in(&text) {
+                if !settings.matches(&url) {
+                    continue;
+                }
+                let url = url.to_string();
+                if !seen.insert(url.clone()) || already_known(&state, &url).await {
+                    continue;
+                }
+                if settings.auto_enqueue {
+                    let result = enqueue_download_internal(
+                        url.clone(),
+                        EnqueueOptions::default(),
+                        state.clone().into(),
+                        app_handle.clone(),
+                    )
+                    .await;
+                    if let Err(e) = result {
+                        eprintln!("Failed to enqueue {} from clipboard: {}", url, e);
+                    }
+                } else {
+                    state.events.emit(DownloadEvent::LinkDetected { url });
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,353 @@
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+
+use glob::{MatchOptions, Pattern};
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::headers::RequestHeaders;
+use crate::resolvers::{anchor_tags, decode_entities, fetch_html};
+
+/// Links with these extensions (or none, when they end in `/`) are pages to
+/// descend into; everything else is a file to download.
+const PAGE_EXTENSIONS: [&str; 8] = ["html", "htm", "xhtml", "shtml", "php", "asp", "aspx", "jsp"];
+
+/// How far a crawl may go and which files it keeps.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CrawlOptions {
+    /// Levels of pages below the start URL to follow; 0 only reads the start
+    /// page itself.
+    pub depth: u32,
+    /// Hosts besides the start URL's that may be crawled; subdomains match too.
+    pub hosts: Vec<String>,
+    /// Don't follow links out of the start URL's folder, such as the
+    /// "Parent Directory" entry of an index listing.
+    pub no_parent: bool,
+    /// Keep only files that match one of these, when any are given. See
+    /// `Filter` for the syntax.
+    pub include: Vec<String>,
+    /// Skip files and folders that match any of these.
+    pub exclude: Vec<String>,
+    /// Stops the crawl after this many pages have been read.
+    pub max_pages: usize,
+    /// Stops the crawl after this many files have been found.
+    pub max_files: usize,
+    #[serde(flatten)]
+    pub headers: RequestHeaders,
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        CrawlOptions {
+            depth: 3,
+            hosts: Vec::new(),
+            no_parent: true,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_pages: 500,
+            max_files: 5000,
+            headers: RequestHeaders::default(),
+        }
+    }
+}
+
+/// A file found by a crawl and where it goes below the download folder.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CrawledFile {
+    pub url: String,
+    /// Relative path, mirroring the remote folders.
+    pub path: PathBuf,
+}
+
+/// An include/exclude pattern. `re:` starts a regular expression, anything
+/// else is a glob (`*`, `?`, `[...]`, `**`). Both are matched against the
+/// path relative to the crawl's folder, e.g. `isos/debian.iso`; a glob
+/// without `/` is matched against the file or folder name alone.
+enum Filter {
+    Glob(Pattern, bool),
+    Regex(Regex),
+}
+
+impl Filter {
+    fn parse(pattern: &str) -> Result<Filter, String> {
+        let pattern = pattern.trim();
+        match pattern.strip_prefix("re:") {
+            Some(re) => Regex::new(re)
+                .map(Filter::Regex)
+                .map_err(|e| format!("Invalid pattern {}: {}", pattern, e)),
+            None => Pattern::new(pattern)
+                .map(|glob| Filter::Glob(glob, pattern.contains('/')))
+                .map_err(|e| format!("Invalid pattern {}: {}", pattern, e)),
+        }
+    }
+
+    fn parse_all(patterns: &[String]) -> Result<Vec<Filter>, String> {
+        patterns
+            .iter()
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| Filter::parse(p))
+            .collect()
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        match self {
+            Filter::Glob(glob, true) => glob.matches_with(
+                path,
+                MatchOptions {
+                    require_literal_separator: true,
+                    ..MatchOptions::new()
+                },
+            ),
+            Filter::Glob(glob, false) => glob.matches(path.rsplit('/').next().unwrap_or(path)),
+            Filter::Regex(re) => re.is_match(path),
+        }
+    }
+}
+
+/// The decoded, non-empty path segments of `url`, without any that could
+/// climb out of the target folder.
+fn segments(url: &Url) -> Vec<String> {
+    url.path_segments()
+        .map(|segments| {
+            segments
+                .map(|s| percent_decode_str(s).decode_utf8_lossy().into_owned())
+                .map(|s| s.replace(['/', '\\', ':'], "_"))
+                .filter(|s| !s.is_empty() && s != "." && s != "..")
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_page(url: &Url) -> bool {
+    let path = url.path();
+    if path.ends_with('/') {
+        return true;
+    }
+    let last = path.rsplit('/').next().unwrap_or_default();
+    last.rsplit_once('.')
+        .is_some_and(|(_, ext)| PAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+struct Crawl {
+    root: Url,
+    /// The start URL's folder, e.g. `/pub/isos/` for `/pub/isos/index.html`.
+    root_dir: String,
+    /// Top-level folder the mirror is written to.
+    root_name: String,
+    /// Path segments in `root_dir`.
+    root_depth: usize,
+    options: CrawlOptions,
+    include: Vec<Filter>,
+    exclude: Vec<Filter>,
+}
+
+impl Crawl {
+    fn new(root: Url, options: CrawlOptions) -> Result<Crawl, String> {
+        if !matches!(root.scheme(), "http" | "https") {
+            return Err("Only http and https URLs can be crawled".to_string());
+        }
+        let root_dir = match root.path().rfind('/') {
+            Some(i) => root.path()[..=i].to_string(),
+            None => "/".to_string(),
+        };
+        let mut dir_segments = root.join(&root_dir).map(|dir| segments(&dir)).unwrap_or_default();
+        let root_depth = dir_segments.len();
+        let root_name = dir_segments
+            .pop()
+            .or_else(|| root.host_str().map(str::to_string))
+            .unwrap_or_else(|| "crawl".to_string());
+        Ok(Crawl {
+            include: Filter::parse_all(&options.include)?,
+            exclude: Filter::parse_all(&options.exclude)?,
+            root,
+            root_dir,
+            root_name,
+            root_depth,
+            options,
+        })
+    }
+
+    fn allowed_host(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        if Some(host.as_str()) == self.root.host_str() {
+            return true;
+        }
+        self.options.hosts.iter().any(|h| {
+            let h = h.trim().trim_start_matches('.').to_ascii_lowercase();
+            !h.is_empty() && (host == h || host.ends_with(&format!(".{}", h)))
+        })
+    }
+
+    fn below_root(&self, url: &Url) -> bool {
+        url.host_str() == self.root.host_str() && url.path().starts_with(&self.root_dir)
+    }
+
+    /// Where `url` goes below the download folder: its path under the
+    /// crawl's folder, or `<host>/<path>` for links outside of it.
+    fn local_path(&self, url: &Url) -> PathBuf {
+        let mut path = PathBuf::new();
+        if self.below_root(url) {
+            path.push(&self.root_name);
+            path.extend(segments(url).into_iter().skip(self.root_depth));
+        } else {
+            path.push(url.host_str().unwrap_or("unknown"));
+            path.extend(segments(url));
+        }
+        path
+    }
+
+    /// `local_path` below the crawl's own folder, with `/` separators, for
+    /// the filters.
+    fn filter_path(&self, url: &Url) -> String {
+        let path = self.local_path(url);
+        let mut parts = path.iter().map(|p| p.to_string_lossy());
+        if self.below_root(url) {
+            parts.next();
+        }
+        parts.collect::<Vec<_>>().join("/")
+    }
+
+    /// Whether a link found on a page should be followed or kept at all.
+    fn in_scope(&self, url: &Url) -> bool {
+        matches!(url.scheme(), "http" | "https")
+            && self.allowed_host(url)
+            && (!self.options.no_parent || self.below_root(url))
+            && !self.exclude.iter().any(|f| f.matches(&self.filter_path(url)))
+    }
+
+    fn wanted(&self, url: &Url) -> bool {
+        self.include.is_empty() || self.include.iter().any(|f| f.matches(&self.filter_path(url)))
+    }
+}
+
+/// The links on a page, resolved against `base`, without fragments and
+/// without the `?C=N;O=D` style sort links of index listings.
+fn page_links(html: &str, base: &Url) -> Vec<Url> {
+    anchor_tags(html)
+        .into_iter()
+        .filter_map(|attrs| attrs.into_iter().find(|(name, _)| name == "href"))
+        .filter_map(|(_, href)| base.join(&decode_entities(&href)).ok())
+        .filter(|url| !(url.query().is_some() && url.path() == base.path()))
+        .map(|mut url| {
+            url.set_fragment(None);
+            url
+        })
+        .collect()
+}
+
+/// Walks HTML directory listings and link pages from `url`, breadth first,
+/// and returns the files below them. Pages that fail to load are logged and
+/// skipped.
+pub async fn crawl(client: &Client, url: &str, options: CrawlOptions) -> Result<Vec<CrawledFile>, String> {
+    let root = Url::parse(url).map_err(|e| e.to_string())?;
+    let crawl = Crawl::new(root.clone(), options)?;
+
+    let mut seen: HashSet<String> = HashSet::from([root.to_string()]);
+    let mut pages = VecDeque::from([(root, 0u32)]);
+    let mut pages_read = 0;
+    let mut files = Vec::new();
+
+    while let Some((page, depth)) = pages.pop_front() {
+        if pages_read >= crawl.options.max_pages || files.len() >= crawl.options.max_files {
+            break;
+        }
+        pages_read += 1;
+        let (base, html) = match fetch_html(client, &page, &crawl.options.headers, &crawl.root).await {
+            Ok(Some(found)) => found,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("Crawl: failed to read {}: {}", page, e);
+                continue;
+            }
+        };
+
+        for link in page_links(&html, &base) {
+            if !seen.insert(link.to_string()) || !crawl.in_scope(&link) {
+                continue;
+            }
+            if is_page(&link) {
+                if depth < crawl.options.depth {
+                    pages.push_back((link, depth + 1));
+                }
+            } else if crawl.wanted(&link) && files.len() < crawl.options.max_files {
+                files.push(CrawledFile {
+                    path: crawl.local_path(&link),
+                    url: link.to_string(),
+                });
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn filter_globs_and_regexes() {
+        let name_glob = Filter::parse("*.iso").unwrap();
+        assert!(name_glob.matches("debian/debian-12.iso"));
+        assert!(!name_glob.matches("debian/debian-12.iso.sig"));
+
+        let path_glob = Filter::parse("isos/*.iso").unwrap();
+        assert!(path_glob.matches("isos/debian.iso"));
+        assert!(!path_glob.matches("isos/old/debian.iso"));
+        assert!(Filter::parse("isos/**/*.iso").unwrap().matches("isos/old/debian.iso"));
+
+        let regex = Filter::parse("re:^docs/.*\\.pdf$").unwrap();
+        assert!(regex.matches("docs/manual.pdf"));
+        assert!(!regex.matches("src/docs/manual.pdf"));
+
+        assert!(Filter::parse("re:(").is_err());
+        assert_eq!(Filter::parse_all(&[" ".to_string(), "*.zip".to_string()]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn segments_are_decoded_and_kept_inside() {
+        assert_eq!(
+            segments(&url("https://example.com/pub/My%20Files//a%2Fb/../c:d/")),
+            vec!["pub", "My Files", "c_d"]
+        );
+        assert_eq!(segments(&url("https://example.com/%2E%2E/x")), vec!["x"]);
+        assert!(segments(&url("https://example.com/")).is_empty());
+    }
+
+    #[test]
+    fn is_page_by_trailing_slash_or_extension() {
+        assert!(is_page(&url("https://example.com/pub/")));
+        assert!(is_page(&url("https://example.com/index.HTML")));
+        assert!(is_page(&url("https://example.com/list.php?page=2")));
+        assert!(!is_page(&url("https://example.com/file.zip")));
+        assert!(!is_page(&url("https://example.com/README")));
+    }
+
+    #[test]
+    fn crawl_scope_and_local_paths() {
+        let options = CrawlOptions {
+            hosts: vec!["cdn.example.org".to_string()],
+            exclude: vec!["*.sig".to_string()],
+            ..CrawlOptions::default()
+        };
+        let crawl = Crawl::new(url("https://example.com/pub/isos/index.html"), options).unwrap();
+
+        let iso = url("https://example.com/pub/isos/12/debian.iso");
+        assert!(crawl.in_scope(&iso));
+        assert_eq!(crawl.local_path(&iso), PathBuf::from("isos/12/debian.iso"));
+        assert_eq!(crawl.filter_path(&iso), "12/debian.iso");
+
+        assert!(!crawl.in_scope(&url("https://example.com/pub/")));
+        assert!(!crawl.in_scope(&url("https://example.com/pub/isos/debian.iso.sig")));
+        assert!(!crawl.in_scope(&url("https://other.com/pub/isos/a.iso")));
+        assert!(crawl.allowed_host(&url("https://eu.cdn.example.org/a.iso")));
+        assert!(Crawl::new(url("ftp://example.com/"), CrawlOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+
+use crate::persistence::save_state;
+use crate::{AppState, DownloadStatus};
+
+const MB: u64 = 1024 * 1024;
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Free bytes on the volume holding `path`. Walks up to the nearest existing
+/// ancestor so it can be asked before the file or its folder is created.
+pub fn available_space(path: &Path) -> Option<u64> {
+    path.ancestors()
+        .find(|p| p.exists())
+        .and_then(|p| fs2::available_space(p).ok())
+}
+
+/// Fails when writing `needed` more bytes under `path` would leave less than
+/// `reserve` bytes free on that volume.
+pub fn check_free_space(path: &Path, needed: u64, reserve: u64) -> Result<(), String> {
+    match available_space(path) {
+        Some(free) if free < needed.saturating_add(reserve) => Err(format!(
+            "Not enough disk space: {} MB needed, {} MB free",
+            needed.saturating_add(reserve) / MB,
+            free / MB
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub fn is_disk_full_error(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::StorageFull
+}
+
+/// Aborts every running transfer and marks it `DiskFull` so it is not
+/// mistaken for a user pause or a failure.
+pub async fn pause_all_for_disk_full(state: &AppState) {
+    let mut handles = state.handles.lock().await;
+    let ids: Vec<u64> = handles.keys().copied().collect();
+    for id in &ids {
+        if let Some(handle) = handles.remove(id) {
+            handle.abort();
+        }
+    }
+    drop(handles);
+
+    let mut downloads = state.downloads.lock().await;
+    for dl in downloads.iter_mut().filter(|d| ids.contains(&d.id)) {
+        dl.status = DownloadStatus::DiskFull;
+        dl.speed_kbps = 0.0;
+        dl.eta_seconds = None;
+        // `Paused` is emitted by the completion handler of each aborted task.
+    }
+}
+
+/// Polls the download directory and pauses everything once free space drops
+/// below `min_free_space_mb`. A threshold of 0 disables the check.
+pub fn spawn_disk_space_watcher(app_handle: AppHandle) {
+    tokio::spawn(async move {
+        let state = app_handle.state::<AppState>().inner().clone();
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+
+            let threshold = *state.min_free_space_mb.lock().await * MB;
+            if threshold == 0 || state.handles.lock().await.is_empty() {
+                continue;
+            }
+
+            let dir = state.download_dir.lock().await.clone();
+            if let Some(free) = available_space(&dir) {
+                if free < threshold {
+                    eprintln!(
+                        "Free space on {} is {} MB, pausing downloads",
+                        dir.display(),
+                        free / MB
+                    );
+                    pause_all_for_disk_full(&state).await;
+                    save_state(&app_handle, &state).await;
+                }
+            }
+        }
+    });
+}
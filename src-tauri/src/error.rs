@@ -0,0 +1,95 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// What went wrong, in a form the UI and scripts can match on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// DNS, connect, TLS or a connection dropped mid-transfer.
+    Network,
+    /// The server answered with a non-success status.
+    Http { status: u16 },
+    /// Reading or writing local files, including a full disk.
+    Filesystem,
+    /// The data doesn't match what was expected (size, ETag, ...).
+    Verification,
+    /// Stopped by the user or the app rather than by a failure.
+    Cancelled,
+    /// Refused by a user script hook.
+    Rejected,
+}
+
+/// Why the last attempt of a download failed. Stored on `Download::error`
+/// and returned by `download_file`; `retryable` decides whether the retry
+/// policy gets a say or the download fails for good.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DownloadError {
+    #[serde(flatten)]
+    pub kind: ErrorKind,
+    pub message: String,
+    pub retryable: bool,
+    /// From the server's `Retry-After`, only meaningful for this attempt.
+    #[serde(skip)]
+    pub retry_after: Option<Duration>,
+}
+
+impl DownloadError {
+    fn new(kind: ErrorKind, message: impl Into<String>, retryable: bool) -> Self {
+        DownloadError {
+            kind,
+            message: message.into(),
+            retryable,
+            retry_after: None,
+        }
+    }
+
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Network, message, true)
+    }
+
+    pub fn http(status: u16, message: impl Into<String>, retryable: bool) -> Self {
+        Self::new(ErrorKind::Http { status }, message, retryable)
+    }
+
+    pub fn filesystem(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Filesystem, message, false)
+    }
+
+    pub fn verification(message: impl Into<String>, retryable: bool) -> Self {
+        Self::new(ErrorKind::Verification, message, retryable)
+    }
+
+    pub fn cancelled() -> Self {
+        Self::new(ErrorKind::Cancelled, "Cancelled", false)
+    }
+
+    pub fn rejected(reason: &str) -> Self {
+        Self::new(ErrorKind::Rejected, format!("Rejected by script: {}", reason), false)
+    }
+
+    pub fn with_retry_after(mut self, retry_after: Option<Duration>) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+}
+
+impl From<reqwest::Error> for DownloadError {
+    fn from(error: reqwest::Error) -> Self {
+        match error.status() {
+            Some(status) => Self::http(status.as_u16(), error.to_string(), false),
+            // Too many redirects or a refused downgrade won't fix itself.
+            None if error.is_redirect() => Self::new(ErrorKind::Network, error.to_string(), false),
+            None => Self::network(error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for DownloadError {}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::{Stream, StreamExt};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::broadcast;
+
+use crate::{AppState, Download, DownloadStatus};
+
+/// Name of the Tauri event every `DownloadEvent` is forwarded on.
+pub const TAURI_EVENT: &str = "download-event";
+
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Something that happened to a download or to the settings. Everything but
+/// `Progress` carries the whole `Download` so consumers can replace their
+/// copy; `Progress` only carries the counters that change while running.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DownloadEvent {
+    Added { download: Download },
+    Started { download: Download },
+    Progress {
+        id: u64,
+        progress: f64,
+        downloaded_bytes: u64,
+        total_bytes: Option<u64>,
+        speed_kbps: f64,
+        eta_seconds: Option<u64>,
+    },
+    Paused { download: Download },
+    Completed { download: Download },
+    Failed { download: Download },
+    /// Anything else about the download changed (queued for a retry,
+    /// extraction progress, post-action results, edited fields, ...).
+    Updated { download: Download },
+    Removed { id: u64 },
+    SettingsChanged { key: String },
+    /// A download link was copied; offered rather than enqueued, see
+    /// `ClipboardSettings::auto_enqueue`.
+    LinkDetected { url: String },
+}
+
+impl DownloadEvent {
+    pub fn progress(download: &Download) -> Self {
+        DownloadEvent::Progress {
+            id: download.id,
+            progress: download.progress,
+            downloaded_bytes: download.downloaded_bytes,
+            total_bytes: download.total_bytes,
+            speed_kbps: download.speed_kbps,
+            eta_seconds: download.eta_seconds,
+        }
+    }
+
+    pub fn settings_changed(key: &str) -> Self {
+        DownloadEvent::SettingsChanged {
+            key: key.to_string(),
+        }
+    }
+
+    pub fn download_id(&self) -> Option<u64> {
+        match self {
+            DownloadEvent::Added { download }
+            | DownloadEvent::Started { download }
+            | DownloadEvent::Paused { download }
+            | DownloadEvent::Completed { download }
+            | DownloadEvent::Failed { download }
+            | DownloadEvent::Updated { download } => Some(download.id),
+            DownloadEvent::Progress { id, .. } | DownloadEvent::Removed { id } => Some(*id),
+            DownloadEvent::SettingsChanged { .. } | DownloadEvent::LinkDetected { .. } => None,
+        }
+    }
+}
+
+/// Fan-out of `DownloadEvent`s to the frontend, the HTTP event stream and
+/// hooks. Progress is coalesced per download to at most one event every
+/// `progress_interval`; lifecycle events always go through.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<DownloadEvent>,
+    progress_interval_ms: Arc<AtomicU64>,
+    last_progress: Arc<Mutex<HashMap<u64, Instant>>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        EventBus {
+            sender,
+            progress_interval_ms: Arc::new(AtomicU64::new(250)),
+            last_progress: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> broadcast::Receiver<DownloadEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Events from now on as a stream. A consumer that falls too far behind
+    /// skips what it missed instead of ending the stream.
+    pub fn stream(&self) -> impl Stream<Item = DownloadEvent> + Send + 'static {
+        futures_util::stream::unfold(self.subscribe(), |mut events| async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => return Some((event, events)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    pub fn progress_interval(&self) -> Duration {
+        Duration::from_millis(self.progress_interval_ms.load(Ordering::Relaxed))
+    }
+
+    pub fn set_progress_interval(&self, interval: Duration) {
+        self.progress_interval_ms
+            .store(interval.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Whether a progress event for `id` would be let through now. Lets the
+    /// download loop skip building one (and locking the list) in between.
+    pub fn progress_due(&self, id: u64) -> bool {
+        let Ok(mut last) = self.last_progress.lock() else {
+            return true;
+        };
+        let now = Instant::now();
+        match last.get(&id) {
+            Some(at) if now.duration_since(*at) < self.progress_interval() => false,
+            _ => {
+                last.insert(id, now);
+                true
+            }
+        }
+    }
+
+    pub fn emit(&self, event: DownloadEvent) {
+        if !matches!(event, DownloadEvent::Progress { .. }) {
+            // The next progress event after a state change goes out at once.
+            if let (Some(id), Ok(mut last)) = (event.download_id(), self.last_progress.lock()) {
+                last.remove(&id);
+            }
+        }
+        // No receivers is fine, nobody is listening yet.
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Follows the event stream to tell when the queue has just run dry: the
+/// last queued or running download finished after something was started,
+/// with no retry waiting. Pausing a download doesn't count.
+#[derive(Debug, Default)]
+pub struct QueueEmptyWatch {
+    busy: bool,
+}
+
+impl QueueEmptyWatch {
+    /// True once per busy period, on the event that leaves nothing queued
+    /// or running.
+    pub async fn observe(&mut self, event: &DownloadEvent, state: &AppState) -> bool {
+        match event {
+            DownloadEvent::Added { .. } | DownloadEvent::Started { .. } => {
+                self.busy = true;
+                false
+            }
+            DownloadEvent::Completed { .. } | DownloadEvent::Failed { .. } | DownloadEvent::Removed { .. } => {
+                if !self.busy {
+                    return false;
+                }
+                // One lock at a time: the enqueue path takes `downloads`
+                // before `queue`.
+                let queued = !state.queue.lock().await.is_empty();
+                let running = !state.active_downloads.lock().await.is_empty();
+                let retrying = state
+                    .downloads
+                    .lock()
+                    .await
+                    .iter()
+                    .any(|d| d.status == DownloadStatus::Queued && d.next_retry_at.is_some());
+                if queued || running || retrying {
+                    return false;
+                }
+                self.busy = false;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Forwards every event to the webview as `download-event`.
+pub fn spawn_tauri_forwarder(app_handle: AppHandle, bus: &EventBus) {
+    let mut events = Box::pin(bus.stream());
+    tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            let _ = app_handle.emit(TAURI_EVENT, &event);
+        }
+    });
+}
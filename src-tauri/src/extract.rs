@@ -0,0 +1,424 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+
+use crate::events::DownloadEvent;
+use crate::persistence::save_state;
+use crate::{stats, AppState};
+
+const MB: u64 = 1024 * 1024;
+/// Unpacking up to this much is always allowed, whatever `max_ratio` says,
+/// so small archives of text or sparse files aren't taken for bombs.
+const MIN_UNPACKED_BYTES: u64 = 100 * MB;
+
+/// Global extraction settings. Extraction is opt-in: either the download
+/// sets `extract`, or its category is listed in `categories`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ExtractSettings {
+    pub categories: Vec<String>,
+    pub delete_archive: bool,
+    /// Stop once this much has been unpacked, whatever the headers claim.
+    pub max_unpacked_mb: u64,
+    /// Stop once unpacked bytes exceed this multiple of the archive size,
+    /// or 100 MB when that is more.
+    pub max_ratio: u64,
+    pub max_entries: u64,
+}
+
+impl Default for ExtractSettings {
+    fn default() -> Self {
+        ExtractSettings {
+            categories: Vec::new(),
+            delete_archive: false,
+            max_unpacked_mb: 50 * 1024,
+            max_ratio: 200,
+            max_entries: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtractionStatus {
+    Extracting,
+    Done,
+    Failed(String),
+}
+
+/// Extraction progress, sent to the frontend as part of the `Download`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Extraction {
+    pub status: ExtractionStatus,
+    pub progress: f64,
+    pub output_dir: PathBuf,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+}
+
+fn detect_format(path: &Path) -> Option<Format> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(Format::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+        Some(Format::TarXz)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else {
+        None
+    }
+}
+
+/// `foo.tar.gz` unpacks into `foo/` next to it, or `foo (1)/` if taken.
+fn output_dir_for(archive: &Path) -> PathBuf {
+    let name = archive.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let lower = name.to_lowercase();
+    let stem_len = [".tar.gz", ".tar.xz", ".tgz", ".txz", ".zip", ".tar"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map(|ext| name.len() - ext.len())
+        .unwrap_or(name.len());
+    let stem = &name[..stem_len];
+    let parent = archive.parent().unwrap_or(Path::new(""));
+
+    let mut dir = parent.join(stem);
+    let mut n = 1;
+    while dir.exists() {
+        dir = parent.join(format!("{} ({})", stem, n));
+        n += 1;
+    }
+    dir
+}
+
+/// Rejects absolute paths and `..` so entries can't escape `dest`.
+fn safe_join(dest: &Path, entry: &Path) -> Option<PathBuf> {
+    let mut out = dest.to_path_buf();
+    for component in entry.components() {
+        match component {
+            Component::Normal(part) => out.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+/// Counts what has been unpacked and enforces the zip-bomb limits.
+struct Budget {
+    written: u64,
+    entries: u64,
+    max_bytes: u64,
+    max_entries: u64,
+}
+
+impl Budget {
+    fn new(settings: &ExtractSettings, archive_size: u64) -> Self {
+        let by_ratio = archive_size
+            .saturating_mul(settings.max_ratio.max(1))
+            .max(MIN_UNPACKED_BYTES);
+        Budget {
+            written: 0,
+            entries: 0,
+            max_bytes: by_ratio.min(settings.max_unpacked_mb.saturating_mul(MB)),
+            max_entries: settings.max_entries,
+        }
+    }
+
+    fn entry(&mut self) -> Result<(), String> {
+        self.entries += 1;
+        if self.entries > self.max_entries {
+            return Err(format!("Archive has more than {} entries", self.max_entries));
+        }
+        Ok(())
+    }
+
+    /// Copies at most the remaining budget, failing if the entry is larger.
+    fn copy(&mut self, reader: &mut dyn Read, out: &Path) -> Result<(), String> {
+        let remaining = self.max_bytes.saturating_sub(self.written);
+        let mut file = File::create(out).map_err(|e| e.to_string())?;
+        let copied = io::copy(&mut reader.take(remaining + 1), &mut file).map_err(|e| e.to_string())?;
+        if copied > remaining {
+            return Err(format!(
+                "Archive expands beyond {} MB, refusing to continue",
+                self.max_bytes / MB
+            ));
+        }
+        self.written += copied;
+        Ok(())
+    }
+}
+
+/// Tracks how much of the compressed archive has been read, for progress.
+struct CountingReader<R> {
+    inner: R,
+    read: u64,
+    total: u64,
+    last_percent: u64,
+    progress: mpsc::UnboundedSender<f64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        if let Some(percent) = (self.read * 100).checked_div(self.total) {
+            if percent > self.last_percent {
+                self.last_percent = percent;
+                let _ = self.progress.send(percent as f64);
+            }
+        }
+        Ok(n)
+    }
+}
+
+fn extract_zip(
+    archive: &Path,
+    dest: &Path,
+    budget: &mut Budget,
+    progress: &mpsc::UnboundedSender<f64>,
+) -> Result<(), String> {
+    let file = File::open(archive).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let count = zip.len();
+
+    for i in 0..count {
+        budget.entry()?;
+        let mut entry = zip.by_index(i).map_err(|e| e.to_string())?;
+        let Some(name) = entry.enclosed_name() else {
+            return Err(format!("Unsafe path in archive: {}", entry.name()));
+        };
+        let Some(out) = safe_join(dest, &name) else {
+            return Err(format!("Unsafe path in archive: {}", entry.name()));
+        };
+        let is_symlink = entry.unix_mode().is_some_and(|mode| mode & 0o170000 == 0o120000);
+
+        if entry.is_dir() {
+            fs::create_dir_all(&out).map_err(|e| e.to_string())?;
+        } else if !is_symlink {
+            if let Some(parent) = out.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            budget.copy(&mut entry, &out)?;
+        }
+        let _ = progress.send((i + 1) as f64 * 100.0 / count as f64);
+    }
+    Ok(())
+}
+
+fn extract_tar(
+    reader: impl Read,
+    dest: &Path,
+    budget: &mut Budget,
+) -> Result<(), String> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries().map_err(|e| e.to_string())? {
+        budget.entry()?;
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path().map_err(|e| e.to_string())?.into_owned();
+        let Some(out) = safe_join(dest, &path) else {
+            return Err(format!("Unsafe path in archive: {}", path.display()));
+        };
+
+        let kind = entry.header().entry_type();
+        if kind.is_dir() {
+            fs::create_dir_all(&out).map_err(|e| e.to_string())?;
+        } else if kind.is_file() {
+            if let Some(parent) = out.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            budget.copy(&mut entry, &out)?;
+        }
+        // Links, devices and other special entries are skipped on purpose.
+    }
+    Ok(())
+}
+
+fn extract_archive(
+    archive: &Path,
+    format: Format,
+    dest: &Path,
+    settings: &ExtractSettings,
+    progress: mpsc::UnboundedSender<f64>,
+) -> Result<(), String> {
+    let size = fs::metadata(archive).map_err(|e| e.to_string())?.len();
+    let mut budget = Budget::new(settings, size);
+    fs::create_dir_all(dest).map_err(|e| e.to_string())?;
+
+    if let Format::Zip = format {
+        return extract_zip(archive, dest, &mut budget, &progress);
+    }
+
+    let reader = CountingReader {
+        inner: File::open(archive).map_err(|e| e.to_string())?,
+        read: 0,
+        total: size,
+        last_percent: 0,
+        progress,
+    };
+    match format {
+        Format::Tar => extract_tar(reader, dest, &mut budget),
+        Format::TarGz => extract_tar(flate2::read::GzDecoder::new(reader), dest, &mut budget),
+        Format::TarXz => extract_tar(xz2::read::XzDecoder::new(reader), dest, &mut budget),
+        Format::Zip => unreachable!(),
+    }
+}
+
+async fn set_extraction(state: &AppState, id: u64, extraction: Extraction) {
+    let mut downloads = state.downloads.lock().await;
+    if let Some(dl) = downloads.iter_mut().find(|d| d.id == id) {
+        dl.extraction = Some(extraction);
+        state.events.emit(DownloadEvent::Updated { download: dl.clone() });
+    }
+}
+
+/// Unpacks a completed download if extraction is enabled for it and the
+/// file is a supported archive. Progress and errors go out as `Updated`
+/// events through `Download::extraction`.
+pub async fn extract_if_enabled(app_handle: AppHandle, id: u64) {
+    let state = app_handle.state::<AppState>().inner().clone();
+    let Some(download) = state.downloads.lock().await.iter().find(|d| d.id == id).cloned() else {
+        return;
+    };
+    let settings = state.extract_settings.lock().await.clone();
+
+    let category = if download.category.is_empty() {
+        stats::category_for_path(&download.file_path)
+    } else {
+        download.category.clone()
+    };
+    let enabled = download
+        .extract
+        .unwrap_or_else(|| settings.categories.iter().any(|c| c.eq_ignore_ascii_case(&category)));
+    let Some(format) = detect_format(&download.file_path) else {
+        return;
+    };
+    if !enabled {
+        return;
+    }
+
+    let archive = download.file_path.clone();
+    let dest = output_dir_for(&archive);
+    set_extraction(
+        &state,
+        id,
+        Extraction {
+            status: ExtractionStatus::Extracting,
+            progress: 0.0,
+            output_dir: dest.clone(),
+        },
+    )
+    .await;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let job = {
+        let (archive, dest, settings) = (archive.clone(), dest.clone(), settings.clone());
+        tokio::task::spawn_blocking(move || extract_archive(&archive, format, &dest, &settings, tx))
+    };
+    while let Some(progress) = rx.recv().await {
+        set_extraction(
+            &state,
+            id,
+            Extraction {
+                status: ExtractionStatus::Extracting,
+                progress,
+                output_dir: dest.clone(),
+            },
+        )
+        .await;
+    }
+
+    let result = job.await.map_err(|e| e.to_string()).and_then(|r| r);
+    let status = match result {
+        Ok(()) => {
+            if settings.delete_archive {
+                let _ = tokio::fs::remove_file(&archive).await;
+            }
+            ExtractionStatus::Done
+        }
+        Err(e) => {
+            eprintln!("Failed to extract {}: {}", archive.display(), e);
+            let _ = tokio::fs::remove_dir_all(&dest).await;
+            ExtractionStatus::Failed(e)
+        }
+    };
+    set_extraction(
+        &state,
+        id,
+        Extraction {
+            progress: if status == ExtractionStatus::Done { 100.0 } else { 0.0 },
+            status,
+            output_dir: dest,
+        },
+    )
+    .await;
+    save_state(&app_handle, &state).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_join_keeps_entries_inside() {
+        let dest = Path::new("/out");
+        assert_eq!(safe_join(dest, Path::new("a/./b.txt")), Some(PathBuf::from("/out/a/b.txt")));
+        assert_eq!(safe_join(dest, Path::new("../evil")), None);
+        assert_eq!(safe_join(dest, Path::new("a/../../evil")), None);
+        assert_eq!(safe_join(dest, Path::new("/etc/passwd")), None);
+    }
+
+    #[test]
+    fn budget_limits_come_from_settings_and_archive_size() {
+        let settings = ExtractSettings {
+            max_unpacked_mb: 1024,
+            max_ratio: 10,
+            ..ExtractSettings::default()
+        };
+        // Small archives may always unpack to `MIN_UNPACKED_BYTES`.
+        assert_eq!(Budget::new(&settings, MB).max_bytes, MIN_UNPACKED_BYTES);
+        assert_eq!(Budget::new(&settings, 50 * MB).max_bytes, 500 * MB);
+        assert_eq!(Budget::new(&settings, 500 * MB).max_bytes, 1024 * MB);
+    }
+
+    #[test]
+    fn budget_counts_entries() {
+        let settings = ExtractSettings {
+            max_entries: 2,
+            ..ExtractSettings::default()
+        };
+        let mut budget = Budget::new(&settings, MB);
+        assert!(budget.entry().is_ok());
+        assert!(budget.entry().is_ok());
+        assert!(budget.entry().is_err());
+    }
+
+    #[test]
+    fn budget_stops_copies_past_the_limit() {
+        let dir = std::env::temp_dir().join(format!("mad_byte-budget-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut budget = Budget {
+            written: 0,
+            entries: 0,
+            max_bytes: 10,
+            max_entries: 10,
+        };
+
+        assert!(budget.copy(&mut &[0u8; 6][..], &dir.join("a")).is_ok());
+        assert_eq!(budget.written, 6);
+        assert!(budget.copy(&mut &[0u8; 4][..], &dir.join("b")).is_ok());
+        assert!(budget.copy(&mut &[0u8; 1][..], &dir.join("c")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,401 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use regex::Regex;
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::events::DownloadEvent;
+use crate::persistence::save_state;
+use crate::{create_download, now_secs, AppState, EnqueueOptions};
+
+/// How often the poller checks whether a feed is due.
+const TICK: Duration = Duration::from_secs(60);
+/// Seen GUIDs kept per feed, newest last. Larger than any sane feed, so an
+/// item still listed is never forgotten.
+const SEEN_LIMIT: usize = 2000;
+/// Feeds larger than this are refused.
+const MAX_FEED_BYTES: usize = 10 * 1024 * 1024;
+
+/// Feeds being checked right now. A second check of the same feed would
+/// work from the same `seen` list and enqueue the same items again.
+static CHECKING: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+/// Holds a feed's place in `CHECKING` until dropped.
+struct CheckGuard(u64);
+
+impl CheckGuard {
+    fn acquire(id: u64) -> Option<CheckGuard> {
+        let mut checking = CHECKING.lock().ok()?;
+        if checking.contains(&id) {
+            return None;
+        }
+        checking.push(id);
+        Some(CheckGuard(id))
+    }
+}
+
+impl Drop for CheckGuard {
+    fn drop(&mut self) {
+        if let Ok(mut checking) = CHECKING.lock() {
+            checking.retain(|&id| id != self.0);
+        }
+    }
+}
+
+pub fn default_interval_minutes() -> u64 {
+    60
+}
+
+/// An RSS or Atom feed whose new enclosures are downloaded on their own.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Feed {
+    pub id: u64,
+    pub url: String,
+    /// The feed's own title until the user renames it.
+    pub title: String,
+    pub enabled: bool,
+    /// Category for the downloads; empty picks one from the file extension.
+    pub category: String,
+    /// Item titles to take, when any are given. See `title_matches`.
+    pub include: Vec<String>,
+    /// Item titles to skip.
+    pub exclude: Vec<String>,
+    /// Also download what is already in the feed when it is added. Otherwise
+    /// the first check only records the current items as seen.
+    pub download_existing: bool,
+    /// GUIDs (or enclosure URLs) of items already handled, oldest first.
+    pub seen: Vec<String>,
+    /// Whether a check has read the feed. Until then its items are the back
+    /// catalogue that `download_existing` is about.
+    pub primed: bool,
+    pub last_checked: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Feed {
+            id: 0,
+            url: String::new(),
+            title: String::new(),
+            enabled: true,
+            category: String::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            download_existing: false,
+            seen: Vec::new(),
+            primed: false,
+            last_checked: None,
+            last_error: None,
+        }
+    }
+}
+
+impl Feed {
+    /// Whether an item with this title passes the include/exclude filters.
+    pub fn wants(&self, title: &str) -> bool {
+        let included = self.include.iter().all(|p| p.trim().is_empty())
+            || self.include.iter().any(|p| title_matches(p, title));
+        included && !self.exclude.iter().any(|p| title_matches(p, title))
+    }
+
+    fn remember(&mut self, guid: String) {
+        self.seen.push(guid);
+        if self.seen.len() > SEEN_LIMIT {
+            let excess = self.seen.len() - SEEN_LIMIT;
+            self.seen.drain(..excess);
+        }
+    }
+}
+
+/// A filter is a case-insensitive piece of the title, or a regular
+/// expression after `re:`. Empty and invalid filters match nothing.
+fn title_matches(pattern: &str, title: &str) -> bool {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return false;
+    }
+    match pattern.strip_prefix("re:") {
+        Some(re) => Regex::new(re).is_ok_and(|re| re.is_match(title)),
+        None => title.to_lowercase().contains(&pattern.to_lowercase()),
+    }
+}
+
+/// One item of a feed that has a file attached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedItem {
+    pub guid: String,
+    pub title: String,
+    pub url: String,
+    pub size: Option<u64>,
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+/// The feed's title and its items with an enclosure, in document order
+/// (usually newest first). Reads RSS 2.0 `<enclosure>`, Atom
+/// `<link rel="enclosure">` and Media RSS `<media:content>`.
+pub fn parse(xml: &str) -> Result<(String, Vec<FeedItem>), String> {
+    let doc = Document::parse(xml).map_err(|e| format!("Invalid feed: {}", e))?;
+    let root = doc.root_element();
+    let (container, item_tag) = match root.tag_name().name() {
+        "rss" => (child(root, "channel").ok_or("RSS feed without a channel")?, "item"),
+        // RSS 1.0 puts items next to the channel.
+        "RDF" => (root, "item"),
+        "feed" => (root, "entry"),
+        _ => return Err("Not an RSS or Atom feed".to_string()),
+    };
+    let title = child(container, "channel")
+        .and_then(|channel| child_text(channel, "title"))
+        .or_else(|| child_text(container, "title"))
+        .unwrap_or_default();
+
+    let mut items = Vec::new();
+    for item in container.children().filter(|n| n.tag_name().name() == item_tag) {
+        let enclosure = item.children().find_map(|n| match n.tag_name().name() {
+            "enclosure" => n.attribute("url").map(|url| (url, n.attribute("length"))),
+            "link" if n.attribute("rel") == Some("enclosure") => {
+                n.attribute("href").map(|url| (url, n.attribute("length")))
+            }
+            "content" if n.tag_name().namespace() != Some("http://www.w3.org/2005/Atom") => {
+                n.attribute("url").map(|url| (url, n.attribute("fileSize")))
+            }
+            _ => None,
+        });
+        let Some((url, length)) = enclosure else {
+            continue;
+        };
+        let url = url.trim().to_string();
+        items.push(FeedItem {
+            guid: child_text(item, "guid")
+                .or_else(|| child_text(item, "id"))
+                .unwrap_or_else(|| url.clone()),
+            title: child_text(item, "title").unwrap_or_default(),
+            size: length.and_then(|l| l.trim().parse().ok()).filter(|&l| l > 0),
+            url,
+        });
+    }
+    Ok((title, items))
+}
+
+async fn fetch(state: &AppState, url: &str) -> Result<String, String> {
+    let client = state.timeouts.lock().await.client();
+    let resp = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| e.to_string())?;
+    let mut body = Vec::new();
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.map_err(|e| e.to_string())?);
+        if body.len() > MAX_FEED_BYTES {
+            return Err("Feed is too large".to_string());
+        }
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Fetches feed `id` and enqueues its new enclosures that pass the filters.
+/// Every new item is marked as seen, downloaded or not, so changing the
+/// filters later only affects new items; only one that failed to enqueue is
+/// tried again next time. Returns how many were enqueued.
+pub async fn check_feed(app_handle: &AppHandle, id: u64) -> Result<usize, String> {
+    let Some(_guard) = CheckGuard::acquire(id) else {
+        return Err("Feed is already being checked".to_string());
+    };
+    let state = app_handle.state::<AppState>().inner().clone();
+    let Some(mut feed) = state.feeds.lock().await.iter().find(|f| f.id == id).cloned() else {
+        return Err("Feed not found".to_string());
+    };
+
+    let first_check = !feed.primed;
+    let parsed = fetch(&state, &feed.url).await.and_then(|xml| parse(&xml));
+    let mut enqueued = 0;
+    let mut error = None;
+    match parsed {
+        Ok((title, items)) => {
+            feed.primed = true;
+            if feed.title.is_empty() {
+                feed.title = title;
+            }
+            // Oldest first, so downloads queue up in publishing order.
+            for item in items.into_iter().rev() {
+                if feed.seen.contains(&item.guid) {
+                    continue;
+                }
+                if (first_check && !feed.download_existing) || !feed.wants(&item.title) {
+                    feed.remember(item.guid);
+                    continue;
+                }
+                let options = EnqueueOptions {
+                    category: Some(feed.category.clone()),
+                    ..EnqueueOptions::default()
+                };
+                match create_download(item.url.clone(), options, &state, true, app_handle.clone()).await {
+                    Ok(id) => {
+                        feed.remember(item.guid);
+                        if let Some(size) = item.size {
+                            if let Some(dl) = state.downloads.lock().await.iter_mut().find(|d| d.id == id) {
+                                dl.total_bytes.get_or_insert(size);
+                            }
+                        }
+                        enqueued += 1;
+                    }
+                    Err(e) => {
+                        eprintln!("Feed {}: failed to enqueue {}: {}", feed.url, item.url, e);
+                        error = Some(e);
+                    }
+                }
+            }
+        }
+        Err(e) => error = Some(e),
+    }
+
+    // The feed may have been edited or removed while it was being fetched;
+    // only the fields the check owns are written back.
+    if let Some(current) = state.feeds.lock().await.iter_mut().find(|f| f.id == id) {
+        if current.title.is_empty() {
+            current.title = feed.title;
+        }
+        current.seen = feed.seen;
+        current.primed = feed.primed;
+        current.last_checked = Some(now_secs());
+        current.last_error = error.clone();
+    }
+    state.events.emit(DownloadEvent::settings_changed("feeds"));
+    save_state(app_handle, &state).await;
+    match error {
+        Some(e) if enqueued == 0 => Err(e),
+        _ => Ok(enqueued),
+    }
+}
+
+/// Checks every enabled feed once its interval has passed since the last
+/// check. Feeds never checked are checked on the first tick.
+pub fn spawn_feed_poller(app_handle: AppHandle) {
+    tokio::spawn(async move {
+        let state = app_handle.state::<AppState>().inner().clone();
+        loop {
+            tokio::time::sleep(TICK).await;
+            let interval = *state.feed_interval_minutes.lock().await * 60;
+            if interval == 0 {
+                continue;
+            }
+            let now = now_secs();
+            let due: Vec<u64> = state
+                .feeds
+                .lock()
+                .await
+                .iter()
+                .filter(|f| f.enabled && f.last_checked.is_none_or(|at| now.saturating_sub(at) >= interval))
+                .map(|f| f.id)
+                .collect();
+            for id in due {
+                if let Err(e) = check_feed(&app_handle, id).await {
+                    eprintln!("Feed check failed: {}", e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rss_enclosures() {
+        let xml = r#"<?xml version="1.0"?>
+            <rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/"><channel>
+              <title> Podcast </title>
+              <item><title>Episode 2</title><guid>ep-2</guid>
+                <enclosure url=" https://example.com/ep2.mp3 " length="1234" type="audio/mpeg"/></item>
+              <item><title>Notes only</title><link>https://example.com/notes</link></item>
+              <item><title>Episode 1</title>
+                <media:content url="https://example.com/ep1.mp3" fileSize="0"/></item>
+            </channel></rss>"#;
+        let (title, items) = parse(xml).unwrap();
+        assert_eq!(title, "Podcast");
+        assert_eq!(
+            items,
+            vec![
+                FeedItem {
+                    guid: "ep-2".to_string(),
+                    title: "Episode 2".to_string(),
+                    url: "https://example.com/ep2.mp3".to_string(),
+                    size: Some(1234),
+                },
+                FeedItem {
+                    guid: "https://example.com/ep1.mp3".to_string(),
+                    title: "Episode 1".to_string(),
+                    url: "https://example.com/ep1.mp3".to_string(),
+                    size: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_atom_enclosure_links() {
+        let xml = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Releases</title>
+              <entry><id>urn:v1</id><title>v1.0</title>
+                <link rel="alternate" href="https://example.com/v1"/>
+                <link rel="enclosure" href="https://example.com/v1.tar.gz" length="99"/>
+                <content type="html">Changes</content></entry>
+            </feed>"#;
+        let (title, items) = parse(xml).unwrap();
+        assert_eq!(title, "Releases");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].guid, "urn:v1");
+        assert_eq!(items[0].url, "https://example.com/v1.tar.gz");
+        assert_eq!(items[0].size, Some(99));
+    }
+
+    #[test]
+    fn parse_rejects_other_documents() {
+        assert!(parse("<html><body/></html>").is_err());
+        assert!(parse("<rss version=\"2.0\"/>").is_err());
+        assert!(parse("not xml").is_err());
+    }
+
+    #[test]
+    fn wants_applies_include_then_exclude() {
+        let mut feed = Feed::default();
+        assert!(feed.wants("Anything"));
+
+        feed.include = vec!["1080p".to_string(), "re:^Show S\\d+".to_string()];
+        feed.exclude = vec!["  ".to_string(), "CAM".to_string()];
+        assert!(feed.wants("Movie 1080P"));
+        assert!(feed.wants("Show S02E01 720p"));
+        assert!(!feed.wants("Movie 720p"));
+        assert!(!feed.wants("Movie 1080p cam"));
+
+        feed.include = vec!["re:(".to_string()];
+        assert!(!feed.wants("Movie 1080p"));
+    }
+
+    #[test]
+    fn remember_keeps_the_newest() {
+        let mut feed = Feed::default();
+        for i in 0..SEEN_LIMIT + 5 {
+            feed.remember(i.to_string());
+        }
+        assert_eq!(feed.seen.len(), SEEN_LIMIT);
+        assert_eq!(feed.seen[0], "5");
+    }
+}
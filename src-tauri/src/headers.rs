@@ -0,0 +1,59 @@
+use reqwest::header::{COOKIE, REFERER, USER_AGENT};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize, Serializer};
+use url::Url;
+
+/// Browser context captured with a link (by the extension or the HTTP API)
+/// and replayed on every request for that download, so links behind a login
+/// or a referer check keep working. Stored with the download in plain text.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RequestHeaders {
+    pub referer: Option<String>,
+    /// A ready-made `Cookie` header value (`name=value; name2=value2`).
+    pub cookies: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// Serializes `headers` with the cookies left out. A download is sent to the
+/// webview, webhooks, the HTTP API and exports, none of which should see its
+/// session cookies; `persistence` stores them with the download itself.
+pub fn without_cookies<S: Serializer>(headers: &RequestHeaders, serializer: S) -> Result<S::Ok, S::Error> {
+    RequestHeaders {
+        cookies: None,
+        ..headers.clone()
+    }
+    .serialize(serializer)
+}
+
+fn present(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn host(url: &str) -> Option<String> {
+    Url::parse(url).ok()?.host_str().map(str::to_ascii_lowercase)
+}
+
+impl RequestHeaders {
+    /// Adds the headers to a request for `url`. The cookies belong to the
+    /// site of `origin`, the link they were captured with, and are only sent
+    /// to that host, never to mirrors or a URL the link redirected to.
+    pub fn apply(&self, mut request: RequestBuilder, url: &str, origin: &str) -> RequestBuilder {
+        if let Some(referer) = present(&self.referer) {
+            request = request.header(REFERER, referer);
+        }
+        if let Some(cookies) = present(&self.cookies) {
+            if host(url).is_some() && host(url) == host(origin) {
+                request = request.header(COOKIE, cookies);
+            }
+        }
+        if let Some(user_agent) = present(&self.user_agent) {
+            request = request.header(USER_AGENT, user_agent);
+        }
+        request
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.referer.is_none() && self.cookies.is_none() && self.user_agent.is_none()
+    }
+}
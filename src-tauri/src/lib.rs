@@ -1,13 +1,53 @@
+mod clipboard;
+mod crawl;
+mod disk_space;
+mod error;
+mod events;
+mod extract;
+mod feeds;
+mod headers;
+mod metalink;
+pub mod native_host;
+mod notifications;
 mod persistence;
+mod post_actions;
+mod redirects;
+mod resolvers;
+mod retry;
+mod scripting;
+mod stats;
+mod store;
+mod timeouts;
+mod transfer;
+mod watch;
 pub mod torrent;
-use persistence::{load_state, save_state};
+mod webhooks;
+use persistence::{load_state, open_store, save_state, spawn_change_tracker};
+use extract::{ExtractSettings, Extraction};
+use post_actions::{ActionResult, PostAction};
+use error::DownloadError;
+use headers::RequestHeaders;
+use events::{DownloadEvent, EventBus};
+use redirects::{RedirectLog, RedirectSettings};
+use resolvers::{Resolution, ResolverInfo, ResolverRegistry, ResolverSettings};
+use retry::RetryPolicy;
+use scripting::{ScriptDownload, ScriptHook, ScriptHost, ScriptSettings, ScriptStatus};
+use timeouts::{StallDetector, TimeoutSettings};
+use transfer::{ImportMode, ImportReport, TransferFormat};
+use clipboard::ClipboardSettings;
+use crawl::{CrawlOptions, CrawledFile};
+use feeds::Feed;
+use notifications::NotificationSettings;
+use watch::WatchFolder;
+use webhooks::Webhook;
 use std::collections::HashSet;
 use std::env::current_dir;
+use std::io::SeekFrom;
 use std::pin::Pin;
 use std::{
     collections::{HashMap, VecDeque},
     fs,
-    path::PathBuf,
+    path::{Component, Path, PathBuf},
     sync::Arc,
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
@@ -21,12 +61,13 @@ use futures_util::{
 };
 use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, State};
 use tokio::{
     fs::{remove_file, OpenOptions},
-    io::AsyncWriteExt,
+    io::{AsyncSeekExt, AsyncWriteExt},
     sync::Mutex,
     task,
+    time::timeout,
 };
 
 #[derive(Debug, Clone)]
@@ -40,7 +81,26 @@ struct AppState {
     max_concurrent_downloads: Arc<Mutex<usize>>, 
     speed_limit: Arc<Mutex<Option<f64>>>,        
     download_dir: Arc<Mutex<PathBuf>>,
-    max_retries: Arc<Mutex<u32>>, 
+    retry_policy: Arc<Mutex<RetryPolicy>>,
+    conflict_policy: Arc<Mutex<ConflictPolicy>>,
+    min_free_space_mb: Arc<Mutex<u64>>,
+    preallocate: Arc<Mutex<bool>>,
+    post_actions: Arc<Mutex<Vec<PostAction>>>,
+    extract_settings: Arc<Mutex<ExtractSettings>>,
+    timeouts: Arc<Mutex<TimeoutSettings>>,
+    redirects: Arc<Mutex<RedirectSettings>>,
+    webhooks: Arc<Mutex<Vec<Webhook>>>,
+    notifications: Arc<Mutex<NotificationSettings>>,
+    clipboard: Arc<Mutex<ClipboardSettings>>,
+    resolvers: Arc<ResolverRegistry>,
+    resolver_settings: Arc<Mutex<ResolverSettings>>,
+    scripts: Arc<ScriptHost>,
+    script_settings: Arc<Mutex<ScriptSettings>>,
+    feeds: Arc<Mutex<Vec<Feed>>>,
+    /// 0 stops polling; feeds can still be checked by hand.
+    feed_interval_minutes: Arc<Mutex<u64>>,
+    watch_folders: Arc<Mutex<Vec<WatchFolder>>>,
+    events: EventBus,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +115,67 @@ struct Download {
     eta_seconds: Option<u64>,
     total_bytes: Option<u64>,
     retries_left: u32, 
+    #[serde(default)]
+    preallocated: bool,
+    /// Empty means "derive from the file extension", see `stats::category_for_path`.
+    #[serde(default)]
+    category: String,
+    #[serde(default)]
+    created_at: Option<u64>,
+    #[serde(default)]
+    started_at: Option<u64>,
+    #[serde(default)]
+    completed_at: Option<u64>,
+    /// Seconds spent actually transferring, across pauses and retries.
+    #[serde(default)]
+    active_secs: u64,
+    /// Overrides the global post-download actions when set.
+    #[serde(default)]
+    post_actions: Option<Vec<PostAction>>,
+    #[serde(default)]
+    action_log: Vec<ActionResult>,
+    #[serde(default)]
+    delete_after: Option<u64>,
+    /// Overrides the per-category extraction setting when set.
+    #[serde(default)]
+    extract: Option<bool>,
+    #[serde(default)]
+    extraction: Option<Extraction>,
+    /// Alternative URLs for the same file, tried in order after `url`.
+    #[serde(default)]
+    mirrors: Vec<String>,
+    /// Index into `url` followed by `mirrors` of the last source that worked.
+    #[serde(default)]
+    source_index: usize,
+    #[serde(default)]
+    etag: Option<String>,
+    /// Where the data actually came from after following redirects.
+    #[serde(default)]
+    effective_url: Option<String>,
+    /// Every URL visited on the way to `effective_url`, starting with the
+    /// requested one. Empty when the source didn't redirect.
+    #[serde(default)]
+    redirect_chain: Vec<String>,
+    /// Overrides the global retry policy when set.
+    #[serde(default)]
+    retry_policy: Option<RetryPolicy>,
+    /// When a scheduled retry will start, while the download waits in the queue.
+    #[serde(default)]
+    next_retry_at: Option<u64>,
+    /// Why the last attempt failed; cleared when a new attempt starts.
+    #[serde(default)]
+    error: Option<DownloadError>,
+    /// Referer, cookies and user agent sent with every request. The cookies
+    /// are only serialized by `persistence`.
+    #[serde(default, serialize_with = "headers::without_cookies")]
+    headers: RequestHeaders,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -65,6 +186,58 @@ enum DownloadStatus {
     Paused,
     Completed,
     Failed(String),
+    #[serde(rename = "disk_full")]
+    DiskFull,
+}
+
+impl DownloadStatus {
+    /// The status as serialized, without a failure's message.
+    fn name(&self) -> &'static str {
+        match self {
+            DownloadStatus::Queued => "queued",
+            DownloadStatus::Downloading => "downloading",
+            DownloadStatus::Paused => "paused",
+            DownloadStatus::Completed => "completed",
+            DownloadStatus::Failed(_) => "failed",
+            DownloadStatus::DiskFull => "disk_full",
+        }
+    }
+}
+
+/// What to do when an enqueued file name is already taken on disk or by
+/// another download.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    #[default]
+    Ask,
+    Rename,
+    Overwrite,
+    Skip,
+    Resume,
+}
+
+/// Optional extras accepted by every enqueue path.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EnqueueOptions {
+    pub save_as: Option<String>,
+    pub mirrors: Vec<String>,
+    /// Overrides the global conflict policy for this download.
+    pub conflict: Option<ConflictPolicy>,
+    /// Accepted flat, as `referer`, `cookies` and `user_agent`.
+    #[serde(flatten)]
+    pub headers: RequestHeaders,
+    /// Overrides the category picked from the file extension.
+    pub category: Option<String>,
+    /// Folder below the download folder, for crawled files. Not accepted
+    /// from the HTTP API.
+    #[serde(skip)]
+    pub subdir: Option<PathBuf>,
+    /// Only overwrite files of downloads the app already tracks. Set for
+    /// requests from the HTTP API.
+    #[serde(skip)]
+    pub tracked_only: bool,
 }
 
 #[tauri::command]
@@ -78,10 +251,11 @@ async fn enqueue_download(
 
 pub async fn enqueue_download_internal(
     url: String,
+    options: EnqueueOptions,
     state: Arc<AppState>,
     app_handle: AppHandle,
 ) -> Result<u64, String> {
-    create_and_start_download_arc(url, state, true, app_handle).await
+    create_resolved_downloads(url, options, &state, true, app_handle).await
 }
 
 #[tauri::command]
@@ -122,6 +296,15 @@ fn from_hex(byte: u8) -> Option<u8> {
         _ => None,
     }
 }
+/// The last component of a user-supplied name, so it can't point outside
+/// the folder it is joined to. `None` when nothing usable is left.
+fn file_name_only(name: &str) -> Option<String> {
+    Path::new(name.trim())
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .filter(|n| !n.is_empty() && n != "." && n != "..")
+}
+
 fn extract_clean_filename(url: &str) -> String {
     match Url::parse(url) {
         Ok(parsed_url) => {
@@ -147,86 +330,270 @@ async fn create_and_start_download(
     auto_start: bool,
     app_handle: AppHandle,
 ) -> Result<u64, String> {
-    use url::Url;
-
-    let parsed_url = Url::parse(&url).map_err(|e| e.to_string())?;
-    let raw_name = parsed_url
-        .path_segments()
-        .and_then(|segments| segments.last())
-        .unwrap_or("download");
-    use percent_encoding::percent_decode;
-
-    let file_name = extract_clean_filename(&url);
+    create_resolved_downloads(url, EnqueueOptions::default(), &state, auto_start, app_handle).await
+}
 
-    let file_path = {
-        let dir = state.download_dir.lock().await.clone();
-        dir.join(file_name)
+/// Runs the URL resolvers first and enqueues every file they find behind
+/// `url`, or `url` itself when none applies. Returns the first download's id.
+/// `save_as` and `mirrors` only carry over when a single file is found.
+async fn create_resolved_downloads(
+    url: String,
+    options: EnqueueOptions,
+    state: &AppState,
+    auto_start: bool,
+    app_handle: AppHandle,
+) -> Result<u64, String> {
+    let settings = state.resolver_settings.lock().await.clone();
+    let client = state.timeouts.lock().await.client();
+    let Some(resolution) = state
+        .resolvers
+        .resolve(&client, &url, &options.headers, &settings)
+        .await
+    else {
+        return create_download(url, options, state, auto_start, app_handle).await;
     };
 
-    let id = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| e.to_string())?
-        .as_secs();
-    let retries = *state.max_retries.lock().await;
-
-    let download = Download {
-        id,
-        url,
-        file_path,
-        progress: 0.0,
-        status: DownloadStatus::Queued,
-        downloaded_bytes: 0,
-        speed_kbps: 0.0,
-        eta_seconds: None,
-        total_bytes: None,
-        retries_left: retries, 
-    };
+    let single = resolution.links.len() == 1;
+    let mut first = None;
+    let mut last_error = None;
+    for link in resolution.links {
+        let save_as = match &options.save_as {
+            Some(name) if single && !name.trim().is_empty() => Some(name.clone()),
+            _ => link.file_name,
+        };
+        let link_options = EnqueueOptions {
+            save_as,
+            mirrors: if single { options.mirrors.clone() } else { Vec::new() },
+            conflict: options.conflict,
+            headers: options.headers.clone(),
+            category: options.category.clone(),
+            subdir: options.subdir.clone(),
+            tracked_only: options.tracked_only,
+        };
+        match create_download(link.url.clone(), link_options, state, auto_start, app_handle.clone()).await {
+            Ok(id) => {
+                if let Some(size) = link.size {
+                    if let Some(dl) = state.downloads.lock().await.iter_mut().find(|d| d.id == id) {
+                        dl.total_bytes.get_or_insert(size);
+                    }
+                }
+                first.get_or_insert(id);
+            }
+            Err(e) => {
+                eprintln!("Failed to enqueue {} from {}: {}", link.url, resolution.resolver, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    first.ok_or_else(|| last_error.unwrap_or_else(|| "Nothing to download".to_string()))
+}
+/// In-progress data is written next to the target as `<name>.part` and only
+/// renamed to the final name once the transfer is complete.
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
 
-    state.downloads.lock().await.push(download.clone());
-    state.queue.lock().await.push_back(download);
+/// Returns `name (1).ext`, `name (2).ext`, ... for the first candidate that
+/// is neither on disk nor claimed by another download.
+fn unique_file_path(path: &Path, downloads: &[Download]) -> PathBuf {
+    let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "download".to_string());
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut n = 1;
+    loop {
+        let candidate = parent.join(format!("{} ({}){}", stem, n, ext));
+        if !file_conflicts(&candidate, downloads) {
+            return candidate;
+        }
+        n += 1;
+    }
+}
 
-    if auto_start {
-        spawn_next_download(
-            state.downloads.clone(),
-            state.handles.clone(),
-            state.queue.clone(),
-            state.active_downloads.clone(),
-            state.max_concurrent_downloads.clone(),
-            
-            
-            app_handle,
-        );
+/// Ids start from the current Unix time and are bumped past any taken one,
+/// so bulk enqueues within the same second stay distinct.
+fn new_download_id(downloads: &[Download]) -> u64 {
+    let mut id = now_secs();
+    while downloads.iter().any(|d| d.id == id) {
+        id += 1;
     }
+    id
+}
 
-    Ok(id)
+fn file_conflicts(path: &Path, downloads: &[Download]) -> bool {
+    path.exists() || part_path(path).exists() || downloads.iter().any(|d| d.file_path == path)
 }
-async fn create_and_start_download_arc(
-    url: String,
-    state: Arc<AppState>,
+
+/// Shared enqueue path for the commands and the HTTP API. Applies the
+/// conflict policy when the target file already exists on disk or belongs to
+/// another download.
+async fn create_download(
+    mut url: String,
+    mut options: EnqueueOptions,
+    state: &AppState,
     auto_start: bool,
     app_handle: AppHandle,
 ) -> Result<u64, String> {
-    use url::Url;
+    let mut file_name = match options.save_as.take().as_deref().and_then(file_name_only) {
+        Some(name) => name,
+        None => extract_clean_filename(&url),
+    };
+    let mut dir = state.download_dir.lock().await.clone();
+    if let Some(subdir) = options.subdir.take() {
+        // Plain folder names only, never a root, prefix or `..`.
+        dir.extend(
+            subdir
+                .components()
+                .filter(|c| matches!(c, Component::Normal(_))),
+        );
+    }
+    let mut category = match options.category.take() {
+        Some(category) if !category.trim().is_empty() => category.trim().to_string(),
+        _ => stats::category_for_path(&dir.join(&file_name)),
+    };
 
-    let parsed_url = Url::parse(&url).map_err(|e| e.to_string())?;
-    let raw_name = parsed_url
-        .path_segments()
-        .and_then(|segments| segments.last())
-        .unwrap_or("download");
-    use percent_encoding::percent_decode;
+    let view = ScriptDownload {
+        url: url.clone(),
+        mirrors: options.mirrors.clone(),
+        file_name: file_name.clone(),
+        dir: dir.to_string_lossy().into_owned(),
+        category: category.clone(),
+        status: "queued".to_string(),
+        referer: options.headers.referer.clone(),
+        ..ScriptDownload::default()
+    };
+    if let Some(changed) = scripting::run_hook(state, ScriptHook::Enqueue, view).await {
+        if let Some(reason) = changed.reject {
+            return Err(DownloadError::rejected(&reason).message);
+        }
+        url = changed.url;
+        options.mirrors = changed.mirrors;
+        // Only a name; the folder comes from `dir`.
+        if let Some(name) = Path::new(&changed.file_name).file_name() {
+            file_name = name.to_string_lossy().into_owned();
+        }
+        if !changed.dir.trim().is_empty() {
+            dir = PathBuf::from(changed.dir);
+        }
+        category = changed.category;
+    }
 
-    let file_name = extract_clean_filename(&url);
+    Url::parse(&url).map_err(|e| e.to_string())?;
+    for mirror in &options.mirrors {
+        Url::parse(mirror).map_err(|e| format!("Invalid mirror {}: {}", mirror, e))?;
+    }
 
-    let file_path = {
-        let dir = state.download_dir.lock().await.clone();
-        dir.join(file_name)
+    let policy = match options.conflict {
+        Some(policy) => policy,
+        None => *state.conflict_policy.lock().await,
     };
+    let mut file_path = dir.join(file_name);
+    let retries = state.retry_policy.lock().await.max_attempts;
 
-    let id = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| e.to_string())?
-        .as_secs();
-    let retries = *state.max_retries.lock().await;
+    let mut downloads_guard = state.downloads.lock().await;
+    let mut downloaded_bytes = 0;
+
+    if file_conflicts(&file_path, &downloads_guard) {
+        match policy {
+            ConflictPolicy::Ask => return Err("File already exists.".to_string()),
+            ConflictPolicy::Skip => {
+                return match downloads_guard.iter().find(|d| d.file_path == file_path) {
+                    Some(existing) => Ok(existing.id),
+                    None => Err("File already exists, skipped.".to_string()),
+                };
+            }
+            ConflictPolicy::Rename => {
+                file_path = unique_file_path(&file_path, &downloads_guard);
+            }
+            ConflictPolicy::Overwrite | ConflictPolicy::Resume => {
+                let tracked = downloads_guard.iter().position(|d| d.file_path == file_path);
+                if let Some(index) = tracked {
+                    let existing = &downloads_guard[index];
+                    if matches!(
+                        existing.status,
+                        DownloadStatus::Queued | DownloadStatus::Downloading
+                    ) {
+                        return Ok(existing.id);
+                    }
+                } else if policy == ConflictPolicy::Overwrite && options.tracked_only {
+                    return Err("File already exists.".to_string());
+                }
+
+                let resume_bytes = if policy == ConflictPolicy::Resume {
+                    fs::metadata(part_path(&file_path))
+                        .or_else(|_| fs::metadata(&file_path))
+                        .map(|m| m.len())
+                        .unwrap_or(0)
+                } else {
+                    let _ = fs::remove_file(&file_path);
+                    let _ = fs::remove_file(part_path(&file_path));
+                    0
+                };
+
+                if let Some(index) = tracked {
+                    let existing = &mut downloads_guard[index];
+                    existing.url = url;
+                    existing.effective_url = None;
+                    existing.redirect_chain.clear();
+                    if !options.mirrors.is_empty() {
+                        existing.mirrors = options.mirrors;
+                        existing.source_index = 0;
+                    }
+                    if !options.headers.is_empty() {
+                        existing.headers = options.headers;
+                    }
+                    existing.progress = 0.0;
+                    existing.downloaded_bytes = resume_bytes;
+                    existing.total_bytes = None;
+                    existing.speed_kbps = 0.0;
+                    existing.eta_seconds = None;
+                    existing.retries_left = existing
+                        .retry_policy
+                        .as_ref()
+                        .map_or(retries, |p| p.max_attempts);
+                    existing.next_retry_at = None;
+                    existing.error = None;
+                    existing.status = DownloadStatus::Queued;
+                    existing.completed_at = None;
+                    existing.delete_after = None;
+                    existing.extraction = None;
+                    if policy == ConflictPolicy::Overwrite {
+                        existing.preallocated = false;
+                        existing.started_at = None;
+                        existing.active_secs = 0;
+                    }
+
+                    let id = existing.id;
+                    state.queue.lock().await.push_back(existing.clone());
+                    emit_event(&app_handle, DownloadEvent::Updated { download: existing.clone() });
+                    drop(downloads_guard);
+
+                    if auto_start {
+                        spawn_next_download(
+                            state.downloads.clone(),
+                            state.handles.clone(),
+                            state.queue.clone(),
+                            state.active_downloads.clone(),
+                            state.max_concurrent_downloads.clone(),
+                            app_handle,
+                        );
+                    }
+                    return Ok(id);
+                }
+                downloaded_bytes = resume_bytes;
+            }
+        }
+    }
+
+    let id = new_download_id(&downloads_guard);
 
     let download = Download {
         id,
@@ -234,14 +601,36 @@ async fn create_and_start_download_arc(
         file_path,
         progress: 0.0,
         status: DownloadStatus::Queued,
-        downloaded_bytes: 0,
+        downloaded_bytes,
         speed_kbps: 0.0,
         eta_seconds: None,
         total_bytes: None,
-        retries_left: retries, 
+        retries_left: retries,
+        preallocated: false,
+        category,
+        created_at: Some(now_secs()),
+        started_at: None,
+        completed_at: None,
+        active_secs: 0,
+        post_actions: None,
+        action_log: Vec::new(),
+        delete_after: None,
+        extract: None,
+        extraction: None,
+        mirrors: options.mirrors,
+        source_index: 0,
+        etag: None,
+        effective_url: None,
+        redirect_chain: Vec::new(),
+        retry_policy: None,
+        next_retry_at: None,
+        error: None,
+        headers: options.headers,
     };
 
-    state.downloads.lock().await.push(download.clone());
+    downloads_guard.push(download.clone());
+    drop(downloads_guard);
+    emit_event(&app_handle, DownloadEvent::Added { download: download.clone() });
     state.queue.lock().await.push_back(download);
 
     if auto_start {
@@ -251,8 +640,6 @@ async fn create_and_start_download_arc(
             state.queue.clone(),
             state.active_downloads.clone(),
             state.max_concurrent_downloads.clone(),
-            
-            
             app_handle,
         );
     }
@@ -371,8 +758,7 @@ async fn pause_download(
             d.status = DownloadStatus::Paused;
             d.speed_kbps = 0.0;
             d.eta_seconds = None;
-            let _ = app_handle.emit("download-progress", d.clone());
-    
+            // The completion handler emits `Paused` once the task is gone.
         }
         drop(downloads);
         *state.is_downloading.lock().await = false;
@@ -382,8 +768,21 @@ async fn pause_download(
 
         Ok(())
     } else {
-
-        Err("Download not found".into())
+        drop(handles);
+        // Not running, but maybe waiting for a scheduled retry.
+        let mut downloads = state.downloads.lock().await;
+        let Some(d) = downloads
+            .iter_mut()
+            .find(|d| d.id == id && d.next_retry_at.is_some())
+        else {
+            return Err("Download not found".into());
+        };
+        d.status = DownloadStatus::Paused;
+        d.next_retry_at = None;
+        emit_event(&app_handle, DownloadEvent::Paused { download: d.clone() });
+        drop(downloads);
+        save_state(&app_handle, &state).await;
+        Ok(())
     }
 }
 
@@ -393,6 +792,7 @@ async fn resume_download(
     state: State<'_, AppState>,
     app_handle: AppHandle,
 ) -> Result<(), String> {
+    let retries = state.retry_policy.lock().await.max_attempts;
     let mut downloads_guard = state.downloads.lock().await;
     let apph = app_handle.clone();
     if let Some(download) = downloads_guard
@@ -400,11 +800,18 @@ async fn resume_download(
         .find(|d| {
             d.id == id
                 && (d.status == DownloadStatus::Paused
+                    || d.status == DownloadStatus::DiskFull
                     || matches!(d.status, DownloadStatus::Failed(_)))
         })
-        .cloned()
+        .map(|d| {
+            // A manual resume starts a fresh round of retries.
+            d.retries_left = d.retry_policy.as_ref().map_or(retries, |p| p.max_attempts);
+            d.next_retry_at = None;
+            d.clone()
+        })
     {
         drop(downloads_guard);
+        emit_event(&app_handle, DownloadEvent::Updated { download: download.clone() });
         state.queue.lock().await.push_front(download);
         spawn_next_download(
             state.downloads.clone(),
@@ -423,6 +830,83 @@ async fn resume_download(
     }
 }
 
+/// Points a paused or failed download at a new URL (e.g. a re-signed CDN
+/// link) and resumes it. The new URL has to report the same size and ETag
+/// as far as they are known, otherwise the partial data would be corrupted.
+async fn refresh_download_url_internal(
+    id: u64,
+    url: String,
+    state: &AppState,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    Url::parse(&url).map_err(|e| format!("Invalid URL: {}", e))?;
+    let download = get_download_by_id(&state.downloads, id)
+        .await
+        .ok_or_else(|| "Download not found".to_string())?;
+    if !matches!(
+        download.status,
+        DownloadStatus::Paused | DownloadStatus::DiskFull | DownloadStatus::Failed(_)
+    ) {
+        return Err("Only paused or failed downloads can be refreshed".to_string());
+    }
+
+    // A one-byte range is enough to learn the size and ETag.
+    let client = state.timeouts.lock().await.client();
+    let probe = download
+        .headers
+        .apply(client.get(&url), &url, &download.url)
+        .header("Range", "bytes=0-0")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !probe.status().is_success() {
+        return Err(format!("{} returned {}", url, probe.status()));
+    }
+    let etag = response_etag(&probe);
+    let remote_total = remote_total_size(&probe, 0);
+    if !same_resource(&download, remote_total, etag.as_deref()) {
+        return Err("The new URL points to a different file (size or ETag mismatch)".to_string());
+    }
+
+    let mut downloads = state.downloads.lock().await;
+    let dl = downloads
+        .iter_mut()
+        .find(|d| d.id == id)
+        .ok_or_else(|| "Download not found".to_string())?;
+    dl.url = url;
+    dl.source_index = 0;
+    dl.effective_url = None;
+    dl.redirect_chain.clear();
+    if etag.is_some() {
+        dl.etag = etag;
+    }
+    let refreshed = dl.clone();
+    drop(downloads);
+    emit_event(&app_handle, DownloadEvent::Updated { download: refreshed.clone() });
+    state.queue.lock().await.push_front(refreshed);
+
+    spawn_next_download(
+        state.downloads.clone(),
+        state.handles.clone(),
+        state.queue.clone(),
+        state.active_downloads.clone(),
+        state.max_concurrent_downloads.clone(),
+        app_handle.clone(),
+    );
+    save_state(&app_handle, state).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn refresh_download_url(
+    id: u64,
+    url: String,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    refresh_download_url_internal(id, url, &state, app_handle).await
+}
+
 #[tauri::command]
 async fn remove_download(
     id: u64,
@@ -437,6 +921,7 @@ async fn remove_download(
     let mut downloads = state.downloads.lock().await;
     if let Some(index) = downloads.iter().position(|d| d.id == id) {
         let file_path = downloads[index].file_path.clone();
+        let _ = remove_file(part_path(&file_path)).await;
         let _ = remove_file(file_path).await;
         downloads.remove(index);
     }
@@ -451,6 +936,7 @@ async fn remove_download(
         *current_id_guard = None;
         *state.is_downloading.lock().await = false;
     }
+    emit_event(&app_handle, DownloadEvent::Removed { id });
     save_state(&app_handle.clone(), &state).await;
     Ok(())
 }
@@ -480,23 +966,24 @@ async fn remove_download_from_list(
         *current_id_guard = None;
         *state.is_downloading.lock().await = false;
     }
+    emit_event(&app_handle, DownloadEvent::Removed { id });
     save_state(&app_handle.clone(), &state).await;
     Ok(())
 }
 
 #[tauri::command]
-async fn check_file_existence(url: String) -> Result<(bool, String), String> {
-    use std::fs;
+async fn check_file_existence(
+    url: String,
+    state: State<'_, AppState>,
+) -> Result<(bool, String), String> {
     use url::Url;
 
-    let parsed_url = Url::parse(&url).map_err(|e| e.to_string())?;
+    Url::parse(&url).map_err(|e| e.to_string())?;
     let file_name = extract_clean_filename(url.as_str());
 
-    let file_path = dirs::download_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(file_name);
+    let file_path = state.download_dir.lock().await.join(file_name);
 
-    let exists = fs::metadata(&file_path).is_ok();
+    let exists = file_conflicts(&file_path, &state.downloads.lock().await);
     Ok((exists, file_path.to_string_lossy().to_string()))
 }
 
@@ -506,128 +993,25 @@ async fn enqueue_download_with_options(
     overwrite: bool,
     resume: bool,
     save_as: String,
+    mirrors: Option<Vec<String>>,
     state: State<'_, AppState>,
     app_handle: AppHandle,
 ) -> Result<u64, String> {
-    use url::Url;
-    let parsed_url = Url::parse(&url).map_err(|e| e.to_string())?;
-
-    let file_name = if !(save_as.clone().trim().is_empty()) {
-        save_as.clone()
-    } else {
-        extract_clean_filename(url.clone().as_str())
-    };
-    
-    
-    
-    let default_dir = state.download_dir.lock().await;
-    let file_path = default_dir.join(&file_name);
-
-    let mut downloads_guard = state.downloads.lock().await;
-
-    
-    if let Some(existing) = downloads_guard
-        .iter_mut()
-        .find(|d| d.file_path == file_path)
-    {
-        if overwrite {
-            let _ = fs::remove_file(&file_path);
-            existing.progress = 0.0;
-            existing.downloaded_bytes = 0;
-            existing.total_bytes = None;
-            existing.speed_kbps = 0.0;
-            existing.eta_seconds = None;
-            existing.status = DownloadStatus::Queued;
-
-            
-            state.queue.lock().await.push_back(existing.clone());
-
-            spawn_next_download(
-                state.downloads.clone(),
-                state.handles.clone(),
-                state.queue.clone(),
-                state.active_downloads.clone(),
-                state.max_concurrent_downloads.clone(),
-                
-                
-                app_handle,
-            );
-
-            return Ok(existing.id);
-        } else if resume {
-            let resume_bytes = match fs::metadata(&file_path) {
-                Ok(meta) => meta.len(),
-                Err(_) => 0,
-            };
-            existing.downloaded_bytes = resume_bytes;
-            existing.status = DownloadStatus::Queued;
-            existing.total_bytes = None;
-            existing.progress = 0.0;
-            existing.eta_seconds = None;
-            existing.speed_kbps = 0.0;
-
-            state.queue.lock().await.push_back(existing.clone());
-
-            spawn_next_download(
-                state.downloads.clone(),
-                state.handles.clone(),
-                state.queue.clone(),
-                state.active_downloads.clone(),
-                state.max_concurrent_downloads.clone(),
-                
-                
-                app_handle,
-            );
-
-            return Ok(existing.id);
-        } else {
-            return Err("File already exists.".to_string());
-        }
-    }
-    
-    let id = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| e.to_string())?
-        .as_secs();
-
-    let downloaded_bytes = if resume && file_path.exists() {
-        match fs::metadata(&file_path) {
-            Ok(meta) => meta.len(),
-            Err(_) => 0,
-        }
+    let conflict = if overwrite {
+        Some(ConflictPolicy::Overwrite)
+    } else if resume {
+        Some(ConflictPolicy::Resume)
     } else {
-        0
+        None
     };
-
-    let download = Download {
-        id,
-        url,
-        file_path: file_path.clone(),
-        progress: 0.0,
-        status: DownloadStatus::Queued,
-        downloaded_bytes,
-        speed_kbps: 0.0,
-        eta_seconds: None,
-        total_bytes: None,
-        retries_left: state.max_retries.lock().await.clone(),
+    let options = EnqueueOptions {
+        save_as: Some(save_as),
+        mirrors: mirrors.unwrap_or_default(),
+        conflict,
+        ..EnqueueOptions::default()
     };
 
-    downloads_guard.push(download.clone());
-    drop(downloads_guard);
-    state.queue.lock().await.push_back(download);
-
-    spawn_next_download(
-        state.downloads.clone(),
-        state.handles.clone(),
-        state.queue.clone(),
-        state.active_downloads.clone(),
-        state.max_concurrent_downloads.clone(),
-        
-        
-        app_handle,
-    );
-
-    Ok(id)
+    create_resolved_downloads(url, options, &state, true, app_handle).await
 }
 
 fn spawn_next_download(
@@ -670,7 +1054,11 @@ fn spawn_next_download(
             let mut downloads_lock = downloads.lock().await;
             if let Some(dl) = downloads_lock.iter_mut().find(|d| d.id == id) {
                 dl.status = DownloadStatus::Downloading;
-                let _ = app_handle.emit("download-progress", dl.clone());
+                dl.error = None;
+                if dl.started_at.is_none() {
+                    dl.started_at = Some(now_secs());
+                }
+                emit_event(&app_handle, DownloadEvent::Started { download: dl.clone() });
             }
             drop(downloads_lock);
 
@@ -685,6 +1073,7 @@ fn spawn_next_download(
             let app_cl = app_handle.clone();
 
             task::spawn(async move {
+                let started = Instant::now();
                 let result = Abortable::new(
                     download_file(next.clone(), downloads_cl.clone(), app_cl.clone()),
                     abort_reg,
@@ -694,26 +1083,85 @@ fn spawn_next_download(
                 handles_cl.lock().await.remove(&id);
                 active_cl.lock().await.remove(&id);
 
+                let global_policy = app_cl.state::<AppState>().retry_policy.lock().await.clone();
+                let mut retry_in = None;
+                let mut failed = false;
                 let mut downloads_lock = downloads_cl.lock().await;
                 if let Some(dl) = downloads_lock.iter_mut().find(|d| d.id == id) {
-                    match result {
+                    dl.active_secs += started.elapsed().as_secs();
+                    let policy = dl.retry_policy.clone().unwrap_or(global_policy);
+                    match &result {
                         Ok(Ok(_)) => {
                             dl.status = DownloadStatus::Completed;
                             dl.progress = 100.0;
+                            dl.completed_at = Some(now_secs());
+                            dl.retries_left = policy.max_attempts;
+                            dl.error = None;
                         }
                         Ok(Err(e)) => {
-                            dl.status = DownloadStatus::Failed(e.to_string());
+                            dl.error = Some(e.clone());
+                            if e.retryable {
+                                if dl.retries_left > 0 {
+                                    let attempt =
+                                        policy.max_attempts.saturating_sub(dl.retries_left) + 1;
+                                    let delay = policy.delay(attempt, e.retry_after);
+                                    dl.retries_left -= 1;
+                                    dl.status = DownloadStatus::Queued;
+                                    dl.next_retry_at = Some(now_secs() + delay.as_secs());
+                                    retry_in = Some(delay);
+                                } else {
+                                    dl.status = DownloadStatus::Failed(e.to_string());
+                                }
+                            } else if dl.status != DownloadStatus::DiskFull {
+                                dl.status = DownloadStatus::Failed(e.to_string());
+                            }
                         }
                         Err(_) => {
-                            dl.status = DownloadStatus::Paused;
+                            if dl.status != DownloadStatus::DiskFull {
+                                dl.status = DownloadStatus::Paused;
+                                dl.error = Some(DownloadError::cancelled());
+                            }
                         }
                     }
                     dl.speed_kbps = 0.0;
                     dl.eta_seconds = None;
-                    let _ = app_cl.emit("download-progress", dl.clone());
+                    failed = matches!(dl.status, DownloadStatus::Failed(_));
+                    let download = dl.clone();
+                    emit_event(
+                        &app_cl,
+                        match download.status {
+                            DownloadStatus::Completed => DownloadEvent::Completed { download },
+                            DownloadStatus::Failed(_) => DownloadEvent::Failed { download },
+                            DownloadStatus::Paused | DownloadStatus::DiskFull => {
+                                DownloadEvent::Paused { download }
+                            }
+                            _ => DownloadEvent::Updated { download },
+                        },
+                    );
                 }
                 drop(downloads_lock); 
-                                      
+
+                if let Some(delay) = retry_in {
+                    tokio::spawn(schedule_retry(app_cl.clone(), id, delay));
+                }
+
+                if matches!(result, Ok(Ok(_))) {
+                    let app = app_cl.clone();
+                    tokio::spawn(async move {
+                        scripting::run_finish_hook(&app, id, ScriptHook::Complete).await;
+                        extract::extract_if_enabled(app.clone(), id).await;
+                        post_actions::run_post_actions(app, id).await;
+                    });
+                } else if failed {
+                    let app = app_cl.clone();
+                    tokio::spawn(async move {
+                        // Failed downloads only get the actions a script asks for.
+                        if scripting::run_finish_hook(&app, id, ScriptHook::Fail).await {
+                            post_actions::run_post_actions(app, id).await;
+                        }
+                    });
+                }
+
                 spawn_next_download(
                     downloads_cl,
                     handles_cl,
@@ -731,16 +1179,44 @@ fn spawn_next_download(
     });
 }
 
-use crate::persistence::{ensure_state_file_exists, get_state_path};
-use tokio::time::{timeout, Duration as TokioDuration};
+/// Puts a download back in the queue once its retry delay has passed,
+/// unless it was paused, resumed or removed in the meantime.
+async fn schedule_retry(app_handle: AppHandle, id: u64, delay: Duration) {
+    tokio::time::sleep(delay).await;
+    let state = app_handle.state::<AppState>().inner().clone();
 
-async fn fail(
-    download: &Download,
-    state: &Arc<Mutex<Vec<Download>>>,
-    app_handle: &AppHandle,
-    message: String,
-) {
-    update_status(state, download.id, DownloadStatus::Failed(message.clone())).await;
+    let mut downloads = state.downloads.lock().await;
+    let Some(dl) = downloads
+        .iter_mut()
+        .find(|d| d.id == id && d.status == DownloadStatus::Queued && d.next_retry_at.is_some())
+    else {
+        return;
+    };
+    dl.next_retry_at = None;
+    let retry = dl.clone();
+    drop(downloads);
+
+    let mut queue = state.queue.lock().await;
+    if !queue.iter().any(|d| d.id == id) {
+        queue.push_back(retry);
+    }
+    drop(queue);
+
+    spawn_next_download(
+        state.downloads.clone(),
+        state.handles.clone(),
+        state.queue.clone(),
+        state.active_downloads.clone(),
+        state.max_concurrent_downloads.clone(),
+        app_handle,
+    );
+}
+
+/// Records a failure that ends the attempt. The `Failed` event is left to
+/// the completion handler in `spawn_next_download`, which settles the status.
+async fn fail(download: &Download, state: &Arc<Mutex<Vec<Download>>>, error: &DownloadError) {
+    update_status(state, download.id, DownloadStatus::Failed(error.message.clone())).await;
+    set_error(state, download.id, error).await;
     update_progress_bytes_speed(
         state,
         download.id,
@@ -751,78 +1227,243 @@ async fn fail(
     )
     .await;
 
-    if let Some(dl) = get_download_by_id(state, download.id).await {
-        let _ = app_handle.emit("download-progress", dl.clone());
-        println!("{} is failed", dl.file_path.display());
-    }
+    eprintln!("{} is failed", download.file_path.display());
 }
 
-async fn emit_status(
-    download: &Download,
-    state: &Arc<Mutex<Vec<Download>>>,
-    app_handle: &AppHandle,
-) {
-    if let Some(updated) = get_download_by_id(state, download.id).await {
-        let _ = app_handle.emit("download-progress", updated);
+/// Like `fail`, but the download is paused until space is freed.
+async fn disk_full(download: &Download, state: &Arc<Mutex<Vec<Download>>>, error: &DownloadError) {
+    update_status(state, download.id, DownloadStatus::DiskFull).await;
+    set_error(state, download.id, error).await;
+    update_progress_bytes_speed(
+        state,
+        download.id,
+        0.0,
+        download.downloaded_bytes,
+        Some(0.0),
+        download.total_bytes,
+    )
+    .await;
+}
+
+async fn set_error(state: &Arc<Mutex<Vec<Download>>>, id: u64, error: &DownloadError) {
+    if let Some(dl) = state.lock().await.iter_mut().find(|d| d.id == id) {
+        dl.error = Some(error.clone());
     }
 }
+
 async fn download_file(
     download: Download,
     state: Arc<Mutex<Vec<Download>>>,
     app_handle: AppHandle,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let client = Client::new();
-
-    let mut file = match OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&download.file_path)
+) -> Result<(), DownloadError> {
+    let timeouts = app_handle.state::<AppState>().timeouts.lock().await.clone();
+    let redirects = app_handle.state::<AppState>().redirects.lock().await.clone();
+    let redirect_log = RedirectLog::default();
+    let client = redirects
+        .apply(timeouts.apply(Client::builder()), &redirect_log)
+        .build()
+        .unwrap_or_else(|_| Client::new());
+    // The queued copy can be stale; size, ETag and mirrors live on the list entry.
+    let mut download = get_download_by_id(&state, download.id)
         .await
-    {
-        Ok(f) => f,
+        .unwrap_or(download);
+
+    let app_state = app_handle.state::<AppState>().inner().clone();
+    let view = ScriptDownload::of(&download);
+    if let Some(changed) = scripting::run_hook(&app_state, ScriptHook::BeforeStart, view).await {
+        if let Some(reason) = changed.reject {
+            return Err(DownloadError::rejected(&reason));
+        }
+        if Url::parse(&changed.url).is_ok() {
+            if changed.url != download.url {
+                download.url = changed.url;
+                download.effective_url = None;
+            }
+            download.mirrors = changed.mirrors;
+            download.source_index = download.source_index.min(download.mirrors.len());
+        }
+        download.category = changed.category;
+        if let Some(dl) = state.lock().await.iter_mut().find(|d| d.id == download.id) {
+            dl.url = download.url.clone();
+            dl.effective_url = download.effective_url.clone();
+            dl.mirrors = download.mirrors.clone();
+            dl.source_index = download.source_index;
+            dl.category = download.category.clone();
+        }
+    }
+
+    let part = part_path(&download.file_path);
+    // Crawls and scripts may target folders that don't exist yet.
+    if let Some(parent) = download.file_path.parent() {
+        let _ = tokio::fs::create_dir_all(parent).await;
+    }
+    if download.downloaded_bytes > 0 && !part.exists() && download.file_path.exists() {
+        // Partial data left in place by a version that wrote to the final name.
+        let _ = tokio::fs::rename(&download.file_path, &part).await;
+    }
+
+    let mut file = match OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&part)
+        .await
+    {
+        Ok(f) => f,
         Err(e) => {
-            update_status(&state, download.id, DownloadStatus::Failed(e.to_string())).await;
-            let _ = app_handle.emit(
-                "download-progress",
-                get_download_by_id(&state, download.id)
-                    .await
-                    .unwrap_or(download),
-            );
-            return Err(Box::new(e));
+            let error = DownloadError::filesystem(e.to_string());
+            fail(&download, &state, &error).await;
+            return Err(error);
         }
     };
 
+    // The .part file on disk is the source of truth for how much we have,
+    // the persisted counter may lag behind it by a few seconds. Preallocated
+    // files are full-size from the start, so fall back to the counter there.
+    let on_disk = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+    let mut resume_from = if download.preallocated {
+        download.downloaded_bytes.min(on_disk)
+    } else {
+        on_disk
+    };
+
     update_status(&state, download.id, DownloadStatus::Downloading).await;
-    let _ = app_handle.emit(
-        "download-progress",
-        get_download_by_id(&state, download.id)
-            .await
-            .unwrap_or(download.clone()),
-    );
 
-    let response = match client
-        .get(&download.url)
-        .header("Range", format!("bytes={}-", download.downloaded_bytes))
-        .send()
+    let policy = match &download.retry_policy {
+        Some(policy) => policy.clone(),
+        None => app_handle.state::<AppState>().retry_policy.lock().await.clone(),
+    };
+
+    let (mut source_index, mut response) =
+        match open_source(
+            &client,
+            &download,
+            resume_from,
+            &policy,
+            &redirect_log,
+            redirects.reuse_effective_url,
+        )
         .await
-    {
-        Ok(resp) => resp,
-        Err(e) => {
-            update_status(&state, download.id, DownloadStatus::Failed(e.to_string())).await;
-            let _ = app_handle.emit(
-                "download-progress",
-                get_download_by_id(&state, download.id)
-                    .await
-                    .unwrap_or(download),
+        {
+            Ok(opened) => opened,
+            Err(e) => {
+                // Retryable failures are settled by the caller.
+                if !e.retryable {
+                    fail(&download, &state, &e).await;
+                }
+                return Err(e);
+            }
+        };
+
+    if resume_from > 0 {
+        let status = response.status();
+        if status != StatusCode::OK && status != StatusCode::PARTIAL_CONTENT {
+            // Neither the rest of the file nor all of it: keep what we have.
+            let error = DownloadError::http(
+                status.as_u16(),
+                format!("{} returned {} for a resumed download", response.url(), status),
+                false,
             );
-            return Err(Box::new(e));
+            fail(&download, &state, &error).await;
+            return Err(error);
         }
-    };
+        let etag = response_etag(&response);
+        let partial = status == StatusCode::PARTIAL_CONTENT;
+        let same = same_resource(
+            &download,
+            remote_total_size(&response, resume_from),
+            etag.as_deref(),
+        );
+        if !partial || !same {
+            // Either the server ignored the Range header and is sending the
+            // whole file, or this source serves something else: start over.
+            if let Err(e) = file.set_len(0).await {
+                let error = DownloadError::filesystem(e.to_string());
+                fail(&download, &state, &error).await;
+                return Err(error);
+            }
+            resume_from = 0;
+            if partial {
+                (source_index, response) = match open_source(
+                    &client,
+                    &download,
+                    0,
+                    &policy,
+                    &redirect_log,
+                    false,
+                )
+                .await
+                {
+                    Ok(opened) => opened,
+                    Err(e) => {
+                        if !e.retryable {
+                            fail(&download, &state, &e).await;
+                        }
+                        return Err(e);
+                    }
+                };
+            }
+        }
+    }
+
+    {
+        let mut dls = state.lock().await;
+        if let Some(dl) = dls.iter_mut().find(|d| d.id == download.id) {
+            dl.source_index = source_index;
+            dl.etag = response_etag(&response);
+
+            let chain = redirect_log.take();
+            let effective = response.url().to_string();
+            if !chain.is_empty() {
+                dl.redirect_chain = chain;
+            } else if effective == source_url(dl, source_index) {
+                dl.redirect_chain.clear();
+            }
+            // Otherwise the cached effective URL was reused and the chain
+            // that led to it still stands.
+            dl.effective_url = Some(effective);
+        }
+    }
 
     let total_size_from_header = response.content_length().unwrap_or(0);
-    let total_file_size = total_size_from_header + download.downloaded_bytes;
+    let total_file_size = total_size_from_header + resume_from;
+
+    if total_size_from_header > 0 {
+        let app_state = app_handle.state::<AppState>();
+        let reserve = *app_state.min_free_space_mb.lock().await * 1024 * 1024;
+        let allocated = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+        let needed = total_file_size.saturating_sub(allocated.max(resume_from));
+
+        if let Err(message) = disk_space::check_free_space(&part, needed, reserve) {
+            let error = DownloadError::filesystem(message);
+            disk_full(&download, &state, &error).await;
+            return Err(error);
+        }
 
-    let downloaded = download.downloaded_bytes;
+        if *app_state.preallocate.lock().await && allocated < total_file_size {
+            if let Err(e) = file.set_len(total_file_size).await {
+                let error = DownloadError::filesystem(e.to_string());
+                if disk_space::is_disk_full_error(&e) {
+                    disk_full(&download, &state, &error).await;
+                } else {
+                    fail(&download, &state, &error).await;
+                }
+                return Err(error);
+            }
+            let mut dls = state.lock().await;
+            if let Some(dl) = dls.iter_mut().find(|d| d.id == download.id) {
+                dl.preallocated = true;
+            }
+        }
+    }
+
+    if let Err(e) = file.seek(SeekFrom::Start(resume_from)).await {
+        let error = DownloadError::filesystem(e.to_string());
+        fail(&download, &state, &error).await;
+        return Err(error);
+    }
+
+    let downloaded = resume_from;
     update_progress_bytes_speed(
         &state,
         download.id,
@@ -832,12 +1473,9 @@ async fn download_file(
         Some(total_file_size),
     )
     .await;
-    let _ = app_handle.emit(
-        "download-progress",
-        get_download_by_id(&state, download.id)
-            .await
-            .unwrap_or(download.clone()),
-    );
+    if let Some(dl) = get_download_by_id(&state, download.id).await {
+        emit_event(&app_handle, DownloadEvent::progress(&dl));
+    }
 
     
     let kbps_limit = *app_handle.state::<AppState>().speed_limit.lock().await;
@@ -854,218 +1492,1048 @@ async fn download_file(
             Box::pin(raw_stream)
         };
 
+    let events = app_handle.state::<AppState>().events.clone();
     let mut downloaded = downloaded;
     let mut last_check = Instant::now();
     let mut bytes_since_last_check = 0u64;
+    let mut speed_kbps = 0.0;
+    let mut stall = StallDetector::new(timeouts.stall_threshold(kbps_limit));
+
+    loop {
+        let next = match timeouts.read_idle() {
+            Some(idle) => match timeout(idle, stream.next()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    let _ = file.flush().await;
+                    return Err(DownloadError::network(format!(
+                        "No data received for {} seconds",
+                        idle.as_secs()
+                    )));
+                }
+            },
+            None => stream.next().await,
+        };
+        let Some(item) = next else {
+            break;
+        };
+        let chunk = match item {
+            Ok(c) => c,
+            Err(e) => {
+                // Whatever made it to disk is kept; the retry resumes from there.
+                let _ = file.flush().await;
+                return Err(DownloadError::from(e));
+            }
+        };
+
+        if let Err(e) = file.write_all(&chunk).await {
+            let error = DownloadError::filesystem(e.to_string());
+            if disk_space::is_disk_full_error(&e) {
+                disk_full(&download, &state, &error).await;
+            } else {
+                fail(&download, &state, &error).await;
+            }
+            return Err(error);
+        }
+
+        downloaded += chunk.len() as u64;
+        bytes_since_last_check += chunk.len() as u64;
+
+        let elapsed = last_check.elapsed();
+        if elapsed >= Duration::from_millis(500) {
+            let kbps = bytes_since_last_check as f64 / 1024.0 / elapsed.as_secs_f64();
+            speed_kbps = kbps;
+            if let Some(message) = stall.sample(kbps) {
+                let _ = file.flush().await;
+                return Err(DownloadError::network(message));
+            }
+            last_check = Instant::now();
+            bytes_since_last_check = 0;
+        }
+
+        // The shared list is only touched when a progress event is due;
+        // resuming relies on the .part file, not on this counter.
+        if events.progress_due(download.id) {
+            let progress = if total_file_size > 0 {
+                (downloaded as f64 / total_file_size as f64) * 100.0
+            } else {
+                0.0
+            };
+            if let Some(event) = update_progress_bytes_speed(
+                &state,
+                download.id,
+                progress,
+                downloaded,
+                Some(speed_kbps),
+                Some(total_file_size),
+            )
+            .await
+            {
+                events.emit(event);
+            }
+        }
+    }
+
+    let progress = if total_file_size > 0 {
+        (downloaded as f64 / total_file_size as f64) * 100.0
+    } else {
+        100.0
+    };
+    update_progress_bytes_speed(
+        &state,
+        download.id,
+        progress,
+        downloaded,
+        None,
+        Some(total_file_size),
+    )
+    .await;
+    if let Err(e) = file.flush().await {
+        let error = DownloadError::filesystem(e.to_string());
+        fail(&download, &state, &error).await;
+        return Err(error);
+    }
+    drop(file);
+
+    if total_size_from_header > 0 && downloaded != total_file_size {
+        let message = format!(
+            "Incomplete download: received {} of {} bytes",
+            downloaded, total_file_size
+        );
+        // The connection closed early; a retry resumes from what we have.
+        return Err(DownloadError::verification(message, true));
+    }
+
+    if let Err(e) = tokio::fs::rename(&part, &download.file_path).await {
+        let error = DownloadError::filesystem(e.to_string());
+        fail(&download, &state, &error).await;
+        return Err(error);
+    }
+
+    let app_state: State<'_, AppState> = app_handle.state::<AppState>();
+    update_status(&state, download.id, DownloadStatus::Completed).await;
+    save_state(&app_handle.clone(), &app_state).await;
+    Ok(())
+}
+/// `url` for index 0, otherwise the matching mirror.
+fn source_url(download: &Download, index: usize) -> &str {
+    match index {
+        0 => &download.url,
+        _ => download.mirrors.get(index - 1).unwrap_or(&download.url),
+    }
+}
+
+/// Tries the download's URL and then each mirror, starting from the last
+/// source that worked, until one answers with a success status. If every
+/// source failed, the error is marked retryable when at least one failure
+/// was a connection error or a status listed in `policy`.
+///
+/// With `reuse_effective_url` a resume first tries the URL the last source
+/// redirected to, and only re-resolves from that source if it is rejected.
+async fn open_source(
+    client: &Client,
+    download: &Download,
+    resume_from: u64,
+    policy: &RetryPolicy,
+    redirect_log: &RedirectLog,
+    reuse_effective_url: bool,
+) -> Result<(usize, reqwest::Response), DownloadError> {
+    let sources: Vec<&String> = std::iter::once(&download.url)
+        .chain(download.mirrors.iter())
+        .collect();
+    let start = download.source_index.min(sources.len() - 1);
+    let mut last_error = None;
+    let mut retryable = false;
+    let mut retry_after = None;
+
+    if let Some(effective) = download.effective_url.as_deref() {
+        if reuse_effective_url && resume_from > 0 && effective != sources[start] {
+            redirect_log.take();
+            match download
+                .headers
+                .apply(client.get(effective), effective, &download.url)
+                .header("Range", format!("bytes={}-", resume_from))
+                .send()
+                .await
+            {
+                Ok(resp) if resp.status().is_success() => return Ok((start, resp)),
+                Ok(resp) => eprintln!("{} returned {}, re-resolving", effective, resp.status()),
+                Err(e) => eprintln!("{} failed, re-resolving: {}", effective, e),
+            }
+        }
+    }
+
+    for offset in 0..sources.len() {
+        let index = (start + offset) % sources.len();
+        redirect_log.take();
+        let error = match download
+            .headers
+            .apply(client.get(sources[index]), sources[index], &download.url)
+            .header("Range", format!("bytes={}-", resume_from))
+            .send()
+            .await
+        {
+            Ok(resp) if resp.status().is_success() => return Ok((index, resp)),
+            Ok(resp) => {
+                let status = resp.status();
+                let message = format!("{} returned {}", sources[index], status);
+                let error = DownloadError::http(
+                    status.as_u16(),
+                    message,
+                    policy.is_retryable_status(status.as_u16()),
+                );
+                if error.retryable {
+                    retry_after = retry_after.max(retry::retry_after(resp.headers()));
+                }
+                error
+            }
+            Err(e) => DownloadError::from(e),
+        };
+        retryable |= error.retryable;
+        if sources.len() > 1 {
+            eprintln!("Source {} failed, trying the next one: {}", sources[index], error);
+        }
+        last_error = Some(error);
+    }
+
+    let mut error = last_error.unwrap_or_else(|| DownloadError::network("No source to download from"));
+    error.retryable = retryable;
+    Err(error.with_retry_after(retry_after))
+}
+
+fn response_etag(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Full size of the remote file, from `Content-Range` on a 206 or
+/// `Content-Length` otherwise.
+fn remote_total_size(response: &reqwest::Response, resume_from: u64) -> Option<u64> {
+    if response.status() == StatusCode::PARTIAL_CONTENT {
+        let from_range = response
+            .headers()
+            .get(reqwest::header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit('/').next())
+            .and_then(|total| total.parse().ok());
+        return from_range.or_else(|| response.content_length().map(|len| len + resume_from));
+    }
+    response.content_length()
+}
+
+/// Partial data is only kept when what we know about the file (size, ETag)
+/// doesn't contradict the new response.
+fn same_resource(download: &Download, remote_total: Option<u64>, etag: Option<&str>) -> bool {
+    let size_matches = match (download.total_bytes, remote_total) {
+        (Some(known), Some(remote)) => known == remote,
+        _ => true,
+    };
+    let etag_matches = match (download.etag.as_deref(), etag) {
+        (Some(known), Some(remote)) => known == remote,
+        _ => true,
+    };
+    size_matches && etag_matches
+}
+
+async fn get_download_by_id(state: &Arc<Mutex<Vec<Download>>>, id: u64) -> Option<Download> {
+    let downloads = state.lock().await;
+    downloads.iter().find(|d| d.id == id).cloned()
+}
+
+async fn update_status(state: &Arc<Mutex<Vec<Download>>>, id: u64, status: DownloadStatus) {
+    let mut downloads = state.lock().await;
+    if let Some(download) = downloads.iter_mut().find(|d| d.id == id) {
+        download.status = status;
+    }
+}
+
+async fn update_progress_bytes_speed(
+    state: &Arc<Mutex<Vec<Download>>>,
+    id: u64,
+    progress: f64,
+    downloaded_bytes: u64,
+    speed_kbps: Option<f64>,
+    total_bytes: Option<u64>,
+) -> Option<DownloadEvent> {
+    let mut downloads = state.lock().await;
+    let download = downloads.iter_mut().find(|d| d.id == id)?;
+    download.progress = progress;
+    download.downloaded_bytes = downloaded_bytes;
+
+    if let Some(total) = total_bytes {
+        download.total_bytes = Some(total);
+    }
+
+    if let Some(speed) = speed_kbps {
+        download.speed_kbps = speed;
+        if let Some(total) = download.total_bytes {
+            if speed > 0.0 && downloaded_bytes < total {
+                let remaining_bytes = total.saturating_sub(downloaded_bytes);
+                let remaining_kb = remaining_bytes as f64 / 1024.0;
+                let eta_secs = (remaining_kb / speed).round() as u64;
+                download.eta_seconds = Some(eta_secs);
+            } else {
+                download.eta_seconds = None;
+            }
+        } else {
+            download.eta_seconds = None;
+        }
+    } else {
+        if download.status != DownloadStatus::Downloading {
+            download.speed_kbps = 0.0;
+            download.eta_seconds = None;
+        }
+    }
+    Some(DownloadEvent::progress(download))
+}
+
+fn emit_event(app_handle: &AppHandle, event: DownloadEvent) {
+    app_handle.state::<AppState>().events.emit(event);
+}
+
+fn builtin_touch(args: &[&str]) {
+    if args.is_empty() {
+        eprintln!("touch: missing file operand");
+        return;
+    }
+    for file in args {
+        if let Err(e) = fs::OpenOptions::new().create(true).write(true).open(file) {
+            eprintln!("touch: cannot touch '{}': {}", file, e);
+        }
+    }
+}
+#[tauri::command]
+async fn set_max_retries(state: State<'_, AppState>, max: u32) -> Result<(), String> {
+    state.retry_policy.lock().await.max_attempts = max;
+    state.events.emit(DownloadEvent::settings_changed("retry_policy"));
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_max_retries(state: State<'_, AppState>) -> Result<u32, String> {
+    Ok(state.retry_policy.lock().await.max_attempts)
+}
+
+/// Why the download's last attempt failed, if it did.
+#[tauri::command]
+async fn get_download_error(
+    id: u64,
+    state: State<'_, AppState>,
+) -> Result<Option<DownloadError>, String> {
+    get_download_by_id(&state.downloads, id)
+        .await
+        .map(|dl| dl.error)
+        .ok_or_else(|| "Download not found".to_string())
+}
+
+#[tauri::command]
+async fn get_progress_interval_ms(state: State<'_, AppState>) -> Result<u64, String> {
+    Ok(state.events.progress_interval().as_millis() as u64)
+}
+
+/// How often each running download may send a progress event.
+#[tauri::command]
+async fn set_progress_interval_ms(
+    value: u64,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    state.events.set_progress_interval(Duration::from_millis(value));
+    state.events.emit(DownloadEvent::settings_changed("progress_interval_ms"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_webhooks(state: State<'_, AppState>) -> Result<Vec<Webhook>, String> {
+    Ok(state.webhooks.lock().await.clone())
+}
+
+/// The checks `set_webhooks` makes, also run on imported settings.
+pub(crate) fn check_webhooks(webhooks: &[Webhook]) -> Result<(), String> {
+    match webhooks.iter().find(|w| reqwest::Url::parse(&w.url).is_err()) {
+        Some(bad) => Err(format!("Invalid webhook URL: {}", bad.url)),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+async fn set_webhooks(
+    webhooks: Vec<Webhook>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    check_webhooks(&webhooks)?;
+    *state.webhooks.lock().await = webhooks;
+    state.events.emit(DownloadEvent::settings_changed("webhooks"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+/// Sends a `test` event to `webhook` (saved or not) and returns the status
+/// it answered with.
+#[tauri::command]
+async fn test_webhook(webhook: Webhook) -> Result<u16, String> {
+    webhooks::test_fire(&webhook).await
+}
+
+#[tauri::command]
+async fn get_notification_settings(
+    state: State<'_, AppState>,
+) -> Result<NotificationSettings, String> {
+    Ok(state.notifications.lock().await.clone())
+}
+
+#[tauri::command]
+async fn set_notification_settings(
+    settings: NotificationSettings,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    *state.notifications.lock().await = settings;
+    state.events.emit(DownloadEvent::settings_changed("notifications"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_clipboard_settings(state: State<'_, AppState>) -> Result<ClipboardSettings, String> {
+    Ok(state.clipboard.lock().await.clone())
+}
+
+#[tauri::command]
+async fn set_clipboard_settings(
+    settings: ClipboardSettings,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    *state.clipboard.lock().await = settings;
+    state.events.emit(DownloadEvent::settings_changed("clipboard"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+/// The registered URL resolvers, in the order they are tried.
+#[tauri::command]
+async fn list_resolvers(state: State<'_, AppState>) -> Result<Vec<ResolverInfo>, String> {
+    Ok(state.resolvers.list())
+}
+
+#[tauri::command]
+async fn get_resolver_settings(state: State<'_, AppState>) -> Result<ResolverSettings, String> {
+    Ok(state.resolver_settings.lock().await.clone())
+}
+
+#[tauri::command]
+async fn set_resolver_settings(
+    settings: ResolverSettings,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    *state.resolver_settings.lock().await = settings;
+    state.events.emit(DownloadEvent::settings_changed("resolvers"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_script_settings(state: State<'_, AppState>) -> Result<ScriptSettings, String> {
+    Ok(state.script_settings.lock().await.clone())
+}
+
+#[tauri::command]
+async fn set_script_settings(
+    settings: ScriptSettings,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    *state.script_settings.lock().await = settings;
+    state.events.emit(DownloadEvent::settings_changed("scripts"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn list_scripts(state: State<'_, AppState>) -> Result<Vec<ScriptStatus>, String> {
+    Ok(state.scripts.statuses())
+}
+
+/// Reloads the `.rhai` files from the scripts folder.
+#[tauri::command]
+async fn reload_scripts(app_handle: AppHandle) -> Result<Vec<ScriptStatus>, String> {
+    tokio::task::spawn_blocking(move || scripting::load_scripts(&app_handle))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn get_scripts_dir(app_handle: AppHandle) -> Result<String, String> {
+    scripting::scripts_dir(&app_handle).map(|dir| dir.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+async fn open_scripts_dir(app_handle: AppHandle) -> Result<(), String> {
+    post_actions::open_file(&scripting::scripts_dir(&app_handle)?)
+}
+
+/// What enqueueing `url` would download, without enqueueing anything.
+/// `None` means the URL is downloaded as it is.
+#[tauri::command]
+async fn resolve_url(url: String, state: State<'_, AppState>) -> Result<Option<Resolution>, String> {
+    let settings = state.resolver_settings.lock().await.clone();
+    let client = state.timeouts.lock().await.client();
+    Ok(state
+        .resolvers
+        .resolve(&client, &url, &RequestHeaders::default(), &settings)
+        .await)
+}
+
+/// The files below a directory listing or link page, without enqueueing
+/// anything.
+#[tauri::command]
+async fn crawl_url(
+    url: String,
+    options: CrawlOptions,
+    state: State<'_, AppState>,
+) -> Result<Vec<CrawledFile>, String> {
+    let client = state.timeouts.lock().await.client();
+    crawl::crawl(&client, &url, options).await
+}
+
+/// Enqueues the files a crawl found, recreating their folders below the
+/// download folder. Returns the new downloads' ids.
+#[tauri::command]
+async fn enqueue_crawled(
+    files: Vec<CrawledFile>,
+    headers: Option<RequestHeaders>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<Vec<u64>, String> {
+    let mut ids = Vec::new();
+    let mut last_error = None;
+    for file in files {
+        // Only plain folder names, the list comes back from the frontend.
+        if !file.path.components().all(|c| matches!(c, Component::Normal(_))) {
+            last_error = Some(format!("Invalid path {}", file.path.display()));
+            continue;
+        }
+        let options = EnqueueOptions {
+            save_as: file.path.file_name().map(|n| n.to_string_lossy().into_owned()),
+            subdir: file.path.parent().map(Path::to_path_buf),
+            headers: headers.clone().unwrap_or_default(),
+            ..EnqueueOptions::default()
+        };
+        match create_download(file.url.clone(), options, &state, true, app_handle.clone()).await {
+            Ok(id) => ids.push(id),
+            Err(e) => {
+                eprintln!("Failed to enqueue crawled {}: {}", file.url, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    match last_error {
+        Some(e) if ids.is_empty() => Err(e),
+        _ => Ok(ids),
+    }
+}
+
+#[tauri::command]
+async fn list_feeds(state: State<'_, AppState>) -> Result<Vec<Feed>, String> {
+    Ok(state.feeds.lock().await.clone())
+}
+
+/// The checks `add_feed` makes, also run on imported settings.
+pub(crate) fn check_feed_url(url: &str) -> Result<(), String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid feed URL {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Feed URL must be http or https: {}", url));
+    }
+    Ok(())
+}
+
+/// Subscribes to a feed and checks it right away.
+#[tauri::command]
+async fn add_feed(
+    mut feed: Feed,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<Feed, String> {
+    feed.url = feed.url.trim().to_string();
+    check_feed_url(&feed.url)?;
+
+    let mut feeds = state.feeds.lock().await;
+    if feeds.iter().any(|f| f.url == feed.url) {
+        return Err("Already subscribed to this feed".to_string());
+    }
+    feed.id = feeds.iter().map(|f| f.id).max().unwrap_or(0) + 1;
+    feed.seen.clear();
+    feed.primed = false;
+    feed.last_checked = None;
+    feed.last_error = None;
+    feeds.push(feed.clone());
+    drop(feeds);
+
+    state.events.emit(DownloadEvent::settings_changed("feeds"));
+    save_state(&app_handle, &state).await;
+    let id = feed.id;
+    tokio::spawn(async move {
+        if let Err(e) = feeds::check_feed(&app_handle, id).await {
+            eprintln!("Feed check failed: {}", e);
+        }
+    });
+    Ok(feed)
+}
+
+/// Updates a feed's settings; its seen history and check status are kept.
+#[tauri::command]
+async fn update_feed(
+    feed: Feed,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    Url::parse(feed.url.trim()).map_err(|e| format!("Invalid feed URL {}: {}", feed.url, e))?;
+    let mut feeds = state.feeds.lock().await;
+    let existing = feeds
+        .iter_mut()
+        .find(|f| f.id == feed.id)
+        .ok_or("Feed not found")?;
+    existing.url = feed.url.trim().to_string();
+    existing.title = feed.title;
+    existing.enabled = feed.enabled;
+    existing.category = feed.category.trim().to_string();
+    existing.include = feed.include;
+    existing.exclude = feed.exclude;
+    existing.download_existing = feed.download_existing;
+    drop(feeds);
+
+    state.events.emit(DownloadEvent::settings_changed("feeds"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn remove_feed(id: u64, state: State<'_, AppState>, app_handle: AppHandle) -> Result<(), String> {
+    state.feeds.lock().await.retain(|f| f.id != id);
+    state.events.emit(DownloadEvent::settings_changed("feeds"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+/// Checks a feed now, regardless of the interval. Returns how many new
+/// downloads were enqueued.
+#[tauri::command]
+async fn check_feed(id: u64, app_handle: AppHandle) -> Result<usize, String> {
+    feeds::check_feed(&app_handle, id).await
+}
+
+#[tauri::command]
+async fn get_feed_interval(state: State<'_, AppState>) -> Result<u64, String> {
+    Ok(*state.feed_interval_minutes.lock().await)
+}
+
+#[tauri::command]
+async fn set_feed_interval(
+    minutes: u64,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    *state.feed_interval_minutes.lock().await = minutes;
+    state.events.emit(DownloadEvent::settings_changed("feed_interval"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_watch_folders(state: State<'_, AppState>) -> Result<Vec<WatchFolder>, String> {
+    Ok(state.watch_folders.lock().await.clone())
+}
+
+/// The checks `set_watch_folders` makes, also run on imported settings.
+/// Folders must be absolute; missing ones are created.
+pub(crate) fn check_watch_folders(folders: &[WatchFolder]) -> Result<(), String> {
+    for folder in folders {
+        if !folder.path.is_absolute() {
+            return Err(format!("Watch folder must be an absolute path: {}", folder.path.display()));
+        }
+        fs::create_dir_all(&folder.path)
+            .map_err(|e| format!("Cannot create {}: {}", folder.path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Folders must be absolute; missing ones are created.
+#[tauri::command]
+async fn set_watch_folders(
+    folders: Vec<WatchFolder>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    check_watch_folders(&folders)?;
+    *state.watch_folders.lock().await = folders;
+    state.events.emit(DownloadEvent::settings_changed("watch_folders"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+/// Writes the download list, or the part of it with one of `statuses`, to
+/// `path`. The format follows the extension unless given.
+#[tauri::command]
+async fn export_downloads(
+    path: PathBuf,
+    format: Option<TransferFormat>,
+    statuses: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let format = format.unwrap_or_else(|| TransferFormat::of_path(&path));
+    let body = transfer::export_downloads(&state, format, &statuses.unwrap_or_default()).await?;
+    fs::write(&path, body).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+#[tauri::command]
+async fn import_downloads(
+    path: PathBuf,
+    format: Option<TransferFormat>,
+    mode: Option<ImportMode>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<ImportReport, String> {
+    let text = fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let format = format.unwrap_or_else(|| TransferFormat::of_path(&path));
+    transfer::import_downloads(&state, &app_handle, &text, format, mode.unwrap_or_default()).await
+}
+
+#[tauri::command]
+async fn export_settings(path: PathBuf, state: State<'_, AppState>) -> Result<(), String> {
+    let profile = transfer::export_settings(&state, true).await?;
+    let body = serde_json::to_string_pretty(&profile).map_err(|e| e.to_string())?;
+    fs::write(&path, body).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+#[tauri::command]
+async fn import_settings(
+    path: PathBuf,
+    mode: Option<ImportMode>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let text = fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let profile = serde_json::from_str(&text).map_err(|e| format!("Invalid settings profile: {}", e))?;
+    transfer::import_settings(&state, &app_handle, profile, mode.unwrap_or_default()).await
+}
 
-    while let Some(item) = stream.next().await {
-        let chunk = match item {
-            Ok(c) => c,
-            Err(e) => {
-                let mut dls = state.lock().await;
-                if let Some(dl) = dls.iter_mut().find(|d| d.id == download.id) {
-                    if dl.retries_left > 0 {
-                        dl.retries_left -= 1;
-                        dl.status = DownloadStatus::Paused;
-
-                        let attempt = 10 - dl.retries_left;
-                        let delay_secs = (2_u64).pow(attempt.min(5)); 
-                        let app = app_handle.clone();
-                        let id = dl.id;
-                        let state_cloned = state.clone();
-
-                        
-                        let _ = app.emit("download-progress", dl.clone());
-
-                        
-                        tokio::spawn(async move {
-                            tokio::time::sleep(Duration::from_secs(delay_secs)).await;
-
-                            
-                            let mut downloads = state_cloned.lock().await;
-                            if let Some(d) = downloads.iter_mut().find(|d| d.id == id) {
-                                d.status = DownloadStatus::Queued;
-                                let _ = app.emit("download-progress", d.clone());
-                            }
+#[tauri::command]
+async fn get_timeouts(state: State<'_, AppState>) -> Result<TimeoutSettings, String> {
+    Ok(state.timeouts.lock().await.clone())
+}
 
-                            let app_state = app.state::<AppState>();
-                            let downloads = app_state.downloads.clone();
-                            let handles = app_state.handles.clone();
-                            let queue = app_state.queue.clone();
-                            let active = app_state.active_downloads.clone();
-                            let max = app_state.max_concurrent_downloads.clone();
+/// Applies from the next download attempt on.
+#[tauri::command]
+async fn set_timeouts(
+    timeouts: TimeoutSettings,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    *state.timeouts.lock().await = timeouts;
+    state.events.emit(DownloadEvent::settings_changed("timeouts"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
 
-                            let queue_cloned = queue.clone(); 
+#[tauri::command]
+async fn get_redirect_settings(state: State<'_, AppState>) -> Result<RedirectSettings, String> {
+    Ok(state.redirects.lock().await.clone())
+}
 
-                            let mut q = queue.lock().await;
-                            if !q.iter().any(|d| d.id == id) {
-                                if let Some(dl) = downloads.lock().await.iter().find(|d| d.id == id)
-                                {
-                                    q.push_back(dl.clone());
-                                }
-                            }
-                            drop(q); 
-
-                            spawn_next_download(
-                                downloads,
-                                handles,
-                                queue_cloned, 
-                                active,
-                                max,
-                                app.clone(),
-                            );
-                        });
-
-                        return Err(Box::new(e));
-                    } else {
-                        
-                        dl.status = DownloadStatus::Failed(e.to_string());
-                        dl.speed_kbps = 0.0;
-                        dl.eta_seconds = None;
-                        let _ = app_handle.emit("download-progress", dl.clone());
-                        return Err(Box::new(e));
-                    }
-                } else {
-                    
-                    return Err(Box::new(e));
-                }
-            }
-        };
+#[tauri::command]
+async fn set_redirect_settings(
+    settings: RedirectSettings,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    *state.redirects.lock().await = settings;
+    state.events.emit(DownloadEvent::settings_changed("redirects"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
 
-        if let Err(e) = file.write_all(&chunk).await {
-            update_status(&state, download.id, DownloadStatus::Failed(e.to_string())).await;
-            let _ = app_handle.emit(
-                "download-progress",
-                get_download_by_id(&state, download.id)
-                    .await
-                    .unwrap_or(download),
-            );
-            return Err(Box::new(e));
-        }
+#[tauri::command]
+async fn get_retry_policy(state: State<'_, AppState>) -> Result<RetryPolicy, String> {
+    Ok(state.retry_policy.lock().await.clone())
+}
 
-        downloaded += chunk.len() as u64;
-        bytes_since_last_check += chunk.len() as u64;
+#[tauri::command]
+async fn set_retry_policy(
+    policy: RetryPolicy,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    *state.retry_policy.lock().await = policy;
+    state.events.emit(DownloadEvent::settings_changed("retry_policy"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
 
-        let elapsed = last_check.elapsed();
-        let mut speed_kbps = None;
+/// `None` clears the override so the global policy applies again.
+#[tauri::command]
+async fn set_download_retry_policy(
+    id: u64,
+    policy: Option<RetryPolicy>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let global_attempts = state.retry_policy.lock().await.max_attempts;
+    let mut downloads = state.downloads.lock().await;
+    let dl = downloads
+        .iter_mut()
+        .find(|d| d.id == id)
+        .ok_or_else(|| "Download not found".to_string())?;
+    dl.retries_left = policy.as_ref().map_or(global_attempts, |p| p.max_attempts);
+    dl.retry_policy = policy;
+    emit_event(&app_handle, DownloadEvent::Updated { download: dl.clone() });
+    drop(downloads);
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+#[tauri::command]
+async fn get_statistics(
+    since: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<stats::DownloadStats, String> {
+    Ok(stats::compute(&state.downloads.lock().await, since))
+}
 
-        if elapsed >= Duration::from_millis(500) {
-            let kbps = bytes_since_last_check as f64 / 1024.0 / elapsed.as_secs_f64();
-            speed_kbps = Some(kbps);
-            last_check = Instant::now();
-            bytes_since_last_check = 0;
-        }
+#[tauri::command]
+async fn set_download_category(
+    id: u64,
+    category: String,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let mut downloads = state.downloads.lock().await;
+    let dl = downloads
+        .iter_mut()
+        .find(|d| d.id == id)
+        .ok_or_else(|| "Download not found".to_string())?;
+    dl.category = category.trim().to_string();
+    emit_event(&app_handle, DownloadEvent::Updated { download: dl.clone() });
+    drop(downloads);
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
 
-        let progress = if total_file_size > 0 {
-            (downloaded as f64 / total_file_size as f64) * 100.0
-        } else {
-            0.0
-        };
+#[tauri::command]
+async fn get_post_actions(state: State<'_, AppState>) -> Result<Vec<PostAction>, String> {
+    Ok(state.post_actions.lock().await.clone())
+}
 
-        update_progress_bytes_speed(
-            &state,
-            download.id,
-            progress,
-            downloaded,
-            speed_kbps,
-            Some(total_file_size),
-        )
-        .await;
+#[tauri::command]
+async fn set_post_actions(
+    actions: Vec<PostAction>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    *state.post_actions.lock().await = actions;
+    state.events.emit(DownloadEvent::settings_changed("post_actions"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
 
-        let _ = app_handle.emit(
-            "download-progress",
-            get_download_by_id(&state, download.id)
-                .await
-                .unwrap_or(download.clone()),
-        );
-    }
-    let app_state: State<'_, AppState> = app_handle.state::<AppState>();
-    update_status(&state, download.id, DownloadStatus::Completed).await;
-    let _ = app_handle.emit(
-        "download-progress",
-        get_download_by_id(&state, download.id)
-            .await
-            .unwrap_or(download),
-    );
-    save_state(&app_handle.clone(), &app_state).await;
+/// `None` clears the override so the download follows the global actions again.
+#[tauri::command]
+async fn set_download_post_actions(
+    id: u64,
+    actions: Option<Vec<PostAction>>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let mut downloads = state.downloads.lock().await;
+    let dl = downloads
+        .iter_mut()
+        .find(|d| d.id == id)
+        .ok_or_else(|| "Download not found".to_string())?;
+    dl.post_actions = actions;
+    drop(downloads);
+    save_state(&app_handle, &state).await;
     Ok(())
 }
-async fn get_download_by_id(state: &Arc<Mutex<Vec<Download>>>, id: u64) -> Option<Download> {
-    let downloads = state.lock().await;
-    downloads.iter().find(|d| d.id == id).cloned()
+
+#[tauri::command]
+async fn get_extract_settings(state: State<'_, AppState>) -> Result<ExtractSettings, String> {
+    Ok(state.extract_settings.lock().await.clone())
 }
 
-async fn update_status(state: &Arc<Mutex<Vec<Download>>>, id: u64, status: DownloadStatus) {
-    let mut downloads = state.lock().await;
-    if let Some(download) = downloads.iter_mut().find(|d| d.id == id) {
-        download.status = status;
-    }
+#[tauri::command]
+async fn set_extract_settings(
+    settings: ExtractSettings,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    *state.extract_settings.lock().await = settings;
+    state.events.emit(DownloadEvent::settings_changed("extract_settings"));
+    save_state(&app_handle, &state).await;
+    Ok(())
 }
 
-async fn update_progress_bytes_speed(
-    state: &Arc<Mutex<Vec<Download>>>,
+/// `None` clears the override so the category setting applies again.
+#[tauri::command]
+async fn set_download_extract(
     id: u64,
-    progress: f64,
-    downloaded_bytes: u64,
-    speed_kbps: Option<f64>,
-    total_bytes: Option<u64>,
-) {
-    let mut downloads = state.lock().await;
-    if let Some(download) = downloads.iter_mut().find(|d| d.id == id) {
-        download.progress = progress;
-        download.downloaded_bytes = downloaded_bytes;
+    extract: Option<bool>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let mut downloads = state.downloads.lock().await;
+    let dl = downloads
+        .iter_mut()
+        .find(|d| d.id == id)
+        .ok_or_else(|| "Download not found".to_string())?;
+    dl.extract = extract;
+    drop(downloads);
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
 
-        if let Some(total) = total_bytes {
-            download.total_bytes = Some(total);
-        }
+/// Replaces the mirror list. Takes effect the next time the download starts.
+#[tauri::command]
+async fn set_download_mirrors(
+    id: u64,
+    mirrors: Vec<String>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    for mirror in &mirrors {
+        Url::parse(mirror).map_err(|e| format!("Invalid mirror {}: {}", mirror, e))?;
+    }
+    let mut downloads = state.downloads.lock().await;
+    let dl = downloads
+        .iter_mut()
+        .find(|d| d.id == id)
+        .ok_or_else(|| "Download not found".to_string())?;
+    dl.mirrors = mirrors;
+    dl.source_index = 0;
+    dl.effective_url = None;
+    dl.redirect_chain.clear();
+    emit_event(&app_handle, DownloadEvent::Updated { download: dl.clone() });
+    drop(downloads);
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
 
-        if let Some(speed) = speed_kbps {
-            download.speed_kbps = speed;
-            if let Some(total) = download.total_bytes {
-                if speed > 0.0 && downloaded_bytes < total {
-                    let remaining_bytes = total.saturating_sub(downloaded_bytes);
-                    let remaining_kb = remaining_bytes as f64 / 1024.0;
-                    let eta_secs = (remaining_kb / speed).round() as u64;
-                    download.eta_seconds = Some(eta_secs);
-                } else {
-                    download.eta_seconds = None;
-                }
-            } else {
-                download.eta_seconds = None;
-            }
-        } else {
-            if download.status != DownloadStatus::Downloading {
-                download.speed_kbps = 0.0;
-                download.eta_seconds = None;
-            }
+/// Fetches a Metalink document and enqueues every file in it, using the
+/// best-ranked URL as the source and the rest as mirrors.
+#[tauri::command]
+async fn enqueue_metalink(
+    url: String,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<Vec<u64>, String> {
+    let client = state.timeouts.lock().await.client();
+    let body = client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| e.to_string())?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+    let files = metalink::parse(&body)?;
+
+    let mut ids = Vec::new();
+    for file in files {
+        let options = EnqueueOptions {
+            save_as: Some(file.name),
+            ..EnqueueOptions::default()
+        };
+        if let Some(id) = enqueue_sources(file.urls, file.size, options, &state, app_handle.clone()).await? {
+            ids.push(id);
         }
     }
+    save_state(&app_handle, &state).await;
+    Ok(ids)
 }
 
-fn builtin_touch(args: &[&str]) {
-    if args.is_empty() {
-        eprintln!("touch: missing file operand");
-        return;
-    }
-    for file in args {
-        if let Err(e) = fs::OpenOptions::new().create(true).write(true).open(file) {
-            eprintln!("touch: cannot touch '{}': {}", file, e);
+/// Enqueues one file known under several URLs (a Metalink entry, torrent web
+/// seeds): the first is the source, the rest become mirrors. `None` when
+/// there is no URL at all.
+async fn enqueue_sources(
+    urls: Vec<String>,
+    size: Option<u64>,
+    mut options: EnqueueOptions,
+    state: &AppState,
+    app_handle: AppHandle,
+) -> Result<Option<u64>, String> {
+    let mut urls = urls.into_iter();
+    let Some(primary) = urls.next() else {
+        return Ok(None);
+    };
+    options.mirrors = urls.collect();
+    let id = create_download(primary, options, state, true, app_handle).await?;
+    if let Some(size) = size {
+        // Lets a mirror serving a different size be caught on resume.
+        if let Some(dl) = state.downloads.lock().await.iter_mut().find(|d| d.id == id) {
+            dl.total_bytes.get_or_insert(size);
         }
     }
+    Ok(Some(id))
 }
+
 #[tauri::command]
-async fn set_max_retries(state: State<'_, AppState>, max: u32) -> Result<(), String> {
-    *state.max_retries.lock().await = max;
+async fn get_conflict_policy(state: State<'_, AppState>) -> Result<ConflictPolicy, String> {
+    Ok(*state.conflict_policy.lock().await)
+}
+
+#[tauri::command]
+async fn set_conflict_policy(
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+    policy: ConflictPolicy,
+) -> Result<(), String> {
+    *state.conflict_policy.lock().await = policy;
+    state.events.emit(DownloadEvent::settings_changed("conflict_policy"));
+    save_state(&app_handle, &state).await;
     Ok(())
 }
+#[tauri::command]
+async fn get_min_free_space_mb(state: State<'_, AppState>) -> Result<u64, String> {
+    Ok(*state.min_free_space_mb.lock().await)
+}
 
 #[tauri::command]
-async fn get_max_retries(state: State<'_, AppState>) -> Result<u32, String> {
-    Ok(*state.max_retries.lock().await)
+async fn set_min_free_space_mb(
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+    value: u64,
+) -> Result<(), String> {
+    *state.min_free_space_mb.lock().await = value;
+    state.events.emit(DownloadEvent::settings_changed("min_free_space_mb"));
+    save_state(&app_handle, &state).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_preallocate(state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(*state.preallocate.lock().await)
+}
+
+#[tauri::command]
+async fn set_preallocate(
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+    enabled: bool,
+) -> Result<(), String> {
+    *state.preallocate.lock().await = enabled;
+    state.events.emit(DownloadEvent::settings_changed("preallocate"));
+    save_state(&app_handle, &state).await;
+    Ok(())
 }
+
 #[tauri::command]
 async fn set_speed_limit(state: State<'_, AppState>, kbps: f64) -> Result<(), ()> {
     *state.speed_limit.lock().await = Some(kbps);
+    state.events.emit(DownloadEvent::settings_changed("speed_limit"));
     Ok(())
 }
 
@@ -1085,6 +2553,7 @@ async fn set_max_concurrent_downloads(
     value: usize,
 ) -> Result<(), String> {
     *state.max_concurrent_downloads.lock().await = value;
+    state.events.emit(DownloadEvent::settings_changed("max_concurrent_downloads"));
     Ok(())
 }
 
@@ -1098,13 +2567,20 @@ async fn get_download_dir(state: State<'_, AppState>) -> Result<String, String>
         .to_string())
 }
 
-#[tauri::command]
-async fn set_download_dir(state: State<'_, AppState>, new_path: String) -> Result<(), String> {
-    let path = PathBuf::from(new_path);
+/// The checks `set_download_dir` makes, also run on imported settings.
+pub(crate) fn check_download_dir(path: &Path) -> Result<(), String> {
     if !path.exists() || !path.is_dir() {
         return Err("Invalid directory".to_string());
     }
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_download_dir(state: State<'_, AppState>, new_path: String) -> Result<(), String> {
+    let path = PathBuf::from(new_path);
+    check_download_dir(&path)?;
     *state.download_dir.lock().await = path;
+    state.events.emit(DownloadEvent::settings_changed("download_dir"));
     Ok(())
 }
 
@@ -1142,10 +2618,43 @@ use warp::reply::{json, Json, with_status, WithStatus};
 #[derive(Debug, Clone, Deserialize)]
 pub struct EnqueueRequest {
     pub url: String,
+    #[serde(flatten)]
+    pub options: EnqueueOptions,
+}
+
+
+
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RefreshRequest {
+    pub id: u64,
+    pub url: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatsQuery {
+    #[serde(default)]
+    pub since: Option<u64>,
+}
 
+/// Query of the `/export` and `/import` routes. `status` is a comma
+/// separated list, e.g. `completed,failed`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TransferQuery {
+    pub format: TransferFormat,
+    pub status: Option<String>,
+    pub mode: ImportMode,
+}
 
+fn error_reply(message: String) -> warp::reply::Response {
+    use warp::Reply;
+    with_status(
+        json(&serde_json::json!({ "status": "error", "message": message })),
+        StatusCode::BAD_REQUEST,
+    )
+    .into_response()
+}
 
 pub fn enqueue_filter(
     state: Arc<AppState>,
@@ -1168,7 +2677,8 @@ async fn handle_enqueue(
     state: Arc<AppState>,
     app_handle: AppHandle,
 ) -> Result<impl warp::Reply, Infallible> {
-    let response: WithStatus<Json> = match enqueue_download_internal(req.url, state, app_handle).await {
+    let options = EnqueueOptions { tracked_only: true, ..req.options };
+    let response: WithStatus<Json> = match enqueue_download_internal(req.url, options, state, app_handle).await {
         Ok(id) => with_status(
             json(&serde_json::json!({ "status": "ok", "id": id })),
             StatusCode::OK,
@@ -1182,6 +2692,9 @@ async fn handle_enqueue(
 }
 
 
+/// Port of the local HTTP API on 127.0.0.1.
+pub const HTTP_API_PORT: u16 = 52345;
+
 pub fn spawn_http_api(app: AppHandle, state: Arc<AppState>) {
     tokio::spawn(async move {
 
@@ -1206,8 +2719,12 @@ pub fn spawn_http_api(app: AppHandle, state: Arc<AppState>) {
                         .and_then(|v| v.as_str())
                         .unwrap_or("")
                         .to_string();
+                    let options = EnqueueOptions {
+                        tracked_only: true,
+                        ..serde_json::from_value(req.clone()).unwrap_or_default()
+                    };
 
-                    match enqueue_download_internal(url, state, app.clone()).await {
+                    match enqueue_download_internal(url, options, state, app.clone()).await {
                         Ok(id) => {
                             // Show the main window when a new download is added
                             if let Some(window) = app.get_window("main") {
@@ -1225,8 +2742,192 @@ pub fn spawn_http_api(app: AppHandle, state: Arc<AppState>) {
                     }
                 }
             );
-        warp::serve(enqueue_route)
-            .run(([127, 0, 0, 1], 52345))
+        let stats_route = warp::path!("stats")
+            .and(warp::get())
+            .and(warp::query::<StatsQuery>())
+            .and(warp::any().map({
+                let s = state.clone();
+                move || s.clone()
+            }))
+            .and_then(|query: StatsQuery, state: Arc<AppState>| async move {
+                let downloads = state.downloads.lock().await;
+                Ok::<_, warp::Rejection>(warp::reply::json(&stats::compute(&downloads, query.since)))
+            });
+
+        let refresh_route = warp::path!("refresh")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and(warp::any().map({
+                let s = state.clone();
+                move || s.clone()
+            }))
+            .and(warp::any().map({
+                let app = app.clone();
+                move || app.clone()
+            }))
+            .and_then(|req: RefreshRequest, state: Arc<AppState>, app: AppHandle| async move {
+                let reply = match refresh_download_url_internal(req.id, req.url, &state, app).await {
+                    Ok(()) => serde_json::json!({ "status": "ok", "id": req.id }),
+                    Err(e) => serde_json::json!({ "status": "error", "message": e }),
+                };
+                Ok::<_, warp::Rejection>(warp::reply::json(&reply))
+            });
+
+        let download_route = warp::path!("downloads" / u64)
+            .and(warp::get())
+            .and(warp::any().map({
+                let s = state.clone();
+                move || s.clone()
+            }))
+            .and_then(|id: u64, state: Arc<AppState>| async move {
+                let reply = match get_download_by_id(&state.downloads, id).await {
+                    Some(dl) => with_status(json(&dl), StatusCode::OK),
+                    None => with_status(
+                        json(&serde_json::json!({ "status": "error", "message": "Download not found" })),
+                        StatusCode::NOT_FOUND,
+                    ),
+                };
+                Ok::<_, warp::Rejection>(reply)
+            });
+
+        // Server-sent events, one JSON `DownloadEvent` per message.
+        let events_route = warp::path!("events")
+            .and(warp::get())
+            .and(warp::any().map({
+                let s = state.clone();
+                move || s.clone()
+            }))
+            .map(|state: Arc<AppState>| {
+                let stream = state.events.stream().map(|event| {
+                    Ok::<_, Infallible>(
+                        warp::sse::Event::default()
+                            .json_data(&event)
+                            .unwrap_or_default(),
+                    )
+                });
+                warp::sse::reply(warp::sse::keep_alive().stream(stream))
+            });
+
+        let export_downloads_route = warp::path!("export" / "downloads")
+            .and(warp::get())
+            .and(warp::query::<TransferQuery>())
+            .and(warp::any().map({
+                let s = state.clone();
+                move || s.clone()
+            }))
+            .and_then(|query: TransferQuery, state: Arc<AppState>| async move {
+                use warp::Reply;
+                let statuses: Vec<String> = query
+                    .status
+                    .map(|s| s.split(',').map(str::to_string).collect())
+                    .unwrap_or_default();
+                let content_type = match query.format {
+                    TransferFormat::Json => "application/json",
+                    TransferFormat::Csv => "text/csv; charset=utf-8",
+                };
+                let reply = match transfer::export_downloads(&state, query.format, &statuses).await {
+                    Ok(body) => warp::reply::with_header(body, "content-type", content_type).into_response(),
+                    Err(e) => error_reply(e),
+                };
+                Ok::<_, warp::Rejection>(reply)
+            });
+
+        let import_downloads_route = warp::path!("import" / "downloads")
+            .and(warp::post())
+            .and(warp::query::<TransferQuery>())
+            .and(warp::header::optional::<String>("content-type"))
+            .and(warp::body::content_length_limit(64 * 1024 * 1024))
+            .and(warp::body::bytes())
+            .and(warp::any().map({
+                let s = state.clone();
+                move || s.clone()
+            }))
+            .and(warp::any().map({
+                let app = app.clone();
+                move || app.clone()
+            }))
+            .and_then(|query: TransferQuery, content_type: Option<String>, body: bytes::Bytes, state: Arc<AppState>, app: AppHandle| async move {
+                use warp::Reply;
+                // Web pages can only send these types after a CORS preflight,
+                // which this API doesn't answer.
+                let expected = match query.format {
+                    TransferFormat::Json => "application/json",
+                    TransferFormat::Csv => "text/csv",
+                };
+                if !content_type.is_some_and(|t| t.to_ascii_lowercase().starts_with(expected)) {
+                    return Ok::<_, warp::Rejection>(error_reply(format!("Content-Type must be {}", expected)));
+                }
+                let text = String::from_utf8_lossy(&body);
+                let reply = match transfer::import_downloads(&state, &app, &text, query.format, query.mode).await {
+                    Ok(report) => json(&report).into_response(),
+                    Err(e) => error_reply(e),
+                };
+                Ok::<_, warp::Rejection>(reply)
+            });
+
+        let export_settings_route = warp::path!("export" / "settings")
+            .and(warp::get())
+            .and(warp::any().map({
+                let s = state.clone();
+                move || s.clone()
+            }))
+            .and_then(|state: Arc<AppState>| async move {
+                use warp::Reply;
+                let reply = match transfer::export_settings(&state, false).await {
+                    Ok(profile) => json(&profile).into_response(),
+                    Err(e) => error_reply(e),
+                };
+                Ok::<_, warp::Rejection>(reply)
+            });
+
+        // A settings profile can set commands to run, so importing one takes
+        // the token from the `api-token` file as `X-MadByte-Token`.
+        let api_token = persistence::api_token(&app)
+            .map_err(|e| eprintln!("Settings import over HTTP is off: {}", e))
+            .ok();
+        let import_settings_route = warp::path!("import" / "settings")
+            .and(warp::post())
+            .and(warp::query::<TransferQuery>())
+            .and(warp::header::optional::<String>("x-madbyte-token"))
+            .and(warp::any().map(move || api_token.clone()))
+            .and(warp::body::content_length_limit(16 * 1024 * 1024))
+            .and(warp::body::json())
+            .and(warp::any().map({
+                let s = state.clone();
+                move || s.clone()
+            }))
+            .and(warp::any().map({
+                let app = app.clone();
+                move || app.clone()
+            }))
+            .and_then(|query: TransferQuery, token: Option<String>, expected: Option<String>, profile: serde_json::Value, state: Arc<AppState>, app: AppHandle| async move {
+                use warp::Reply;
+                if expected.is_none() || token != expected {
+                    return Ok::<_, warp::Rejection>(with_status(
+                        json(&serde_json::json!({ "status": "error", "message": "Missing or wrong X-MadByte-Token" })),
+                        StatusCode::UNAUTHORIZED,
+                    )
+                    .into_response());
+                }
+                let reply = match transfer::import_settings(&state, &app, profile, query.mode).await {
+                    Ok(()) => json(&serde_json::json!({ "status": "ok" })).into_response(),
+                    Err(e) => error_reply(e),
+                };
+                Ok::<_, warp::Rejection>(reply)
+            });
+
+        warp::serve(
+            enqueue_route
+                .or(stats_route)
+                .or(refresh_route)
+                .or(download_route)
+                .or(events_route)
+                .or(export_downloads_route)
+                .or(import_downloads_route)
+                .or(export_settings_route)
+                .or(import_settings_route),
+        )
+            .run(([127, 0, 0, 1], HTTP_API_PORT))
             .await;
     });
 }
@@ -1247,7 +2948,25 @@ pub async fn run() {
         active_downloads: Arc::new(Mutex::new(HashSet::new())),
         max_concurrent_downloads: Arc::new(Mutex::new(3)), 
         download_dir: Arc::new(Mutex::new(default_download_dir)),
-        max_retries: Arc::new(Mutex::new(10)),
+        retry_policy: Arc::new(Mutex::new(RetryPolicy::default())),
+        conflict_policy: Arc::new(Mutex::new(ConflictPolicy::default())),
+        min_free_space_mb: Arc::new(Mutex::new(persistence::default_min_free_space_mb())),
+        preallocate: Arc::new(Mutex::new(false)),
+        post_actions: Arc::new(Mutex::new(Vec::new())),
+        extract_settings: Arc::new(Mutex::new(ExtractSettings::default())),
+        timeouts: Arc::new(Mutex::new(TimeoutSettings::default())),
+        redirects: Arc::new(Mutex::new(RedirectSettings::default())),
+        webhooks: Arc::new(Mutex::new(Vec::new())),
+        notifications: Arc::new(Mutex::new(NotificationSettings::default())),
+        clipboard: Arc::new(Mutex::new(ClipboardSettings::default())),
+        resolvers: Arc::new(ResolverRegistry::default()),
+        resolver_settings: Arc::new(Mutex::new(ResolverSettings::default())),
+        scripts: Arc::new(ScriptHost::default()),
+        script_settings: Arc::new(Mutex::new(ScriptSettings::default())),
+        feeds: Arc::new(Mutex::new(Vec::new())),
+        feed_interval_minutes: Arc::new(Mutex::new(feeds::default_interval_minutes())),
+        watch_folders: Arc::new(Mutex::new(Vec::new())),
+        events: EventBus::default(),
     };
 
         
@@ -1259,12 +2978,12 @@ pub async fn run() {
                 ))
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_notification::init())
         .manage(app_state.clone())
         .setup(|app| {
-            if let Err(e) = ensure_state_file_exists() {
-                eprintln!("Failed to create default state.json: {}", e);
-            }
+            app.manage(open_store(app.handle()));
+            spawn_change_tracker(app.handle());
             let handle_owned = app.handle().clone();
 
             let state_owned = app.state::<AppState>().inner().clone();
@@ -1276,7 +2995,18 @@ pub async fn run() {
             let state_owned2 = app.state::<AppState>().inner().clone();
             
 
+            events::spawn_tauri_forwarder(app_handle.clone(), &state_owned2.events);
             spawn_http_api(app_handle.clone(), state_owned2.into()); 
+            disk_space::spawn_disk_space_watcher(app_handle.clone());
+            if let Err(e) = scripting::load_scripts(app_handle) {
+                eprintln!("Failed to load scripts: {}", e);
+            }
+            webhooks::spawn_webhook_dispatcher(app_handle.clone());
+            notifications::spawn_notifier(app_handle.clone());
+            clipboard::spawn_clipboard_watcher(app_handle.clone());
+            feeds::spawn_feed_poller(app_handle.clone());
+            watch::spawn_folder_watcher(app_handle.clone());
+            post_actions::spawn_expiry_sweeper(app_handle.clone());
 
             Ok(())
         })
@@ -1301,7 +3031,66 @@ pub async fn run() {
             get_download_dir,
             set_download_dir,
             set_max_retries,
-            get_max_retries
+            get_max_retries,
+            get_conflict_policy,
+            set_conflict_policy,
+            get_min_free_space_mb,
+            set_min_free_space_mb,
+            get_preallocate,
+            set_preallocate,
+            get_statistics,
+            set_download_category,
+            get_post_actions,
+            set_post_actions,
+            set_download_post_actions,
+            get_extract_settings,
+            set_extract_settings,
+            set_download_extract,
+            set_download_mirrors,
+            enqueue_metalink,
+            refresh_download_url,
+            get_retry_policy,
+            set_retry_policy,
+            set_download_retry_policy,
+            get_download_error,
+            get_timeouts,
+            set_timeouts,
+            get_redirect_settings,
+            set_redirect_settings,
+            get_progress_interval_ms,
+            set_progress_interval_ms,
+            get_webhooks,
+            set_webhooks,
+            test_webhook,
+            get_notification_settings,
+            set_notification_settings,
+            get_clipboard_settings,
+            set_clipboard_settings,
+            list_resolvers,
+            get_resolver_settings,
+            set_resolver_settings,
+            resolve_url,
+            get_script_settings,
+            set_script_settings,
+            list_scripts,
+            reload_scripts,
+            get_scripts_dir,
+            open_scripts_dir,
+            crawl_url,
+            enqueue_crawled,
+            list_feeds,
+            add_feed,
+            update_feed,
+            remove_feed,
+            check_feed,
+            get_feed_interval,
+            set_feed_interval,
+            get_watch_folders,
+            set_watch_folders,
+            export_downloads,
+            import_downloads,
+            export_settings,
+            import_settings
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1321,7 +3110,25 @@ fn main() {
         active_downloads: Arc::new(Mutex::new(HashSet::new())),
         max_concurrent_downloads: Arc::new(Mutex::new(3)), 
         download_dir: Arc::new(Mutex::new(default_download_dir)),
-        max_retries: Arc::new(Mutex::new(10)),
+        retry_policy: Arc::new(Mutex::new(RetryPolicy::default())),
+        conflict_policy: Arc::new(Mutex::new(ConflictPolicy::default())),
+        min_free_space_mb: Arc::new(Mutex::new(persistence::default_min_free_space_mb())),
+        preallocate: Arc::new(Mutex::new(false)),
+        post_actions: Arc::new(Mutex::new(Vec::new())),
+        extract_settings: Arc::new(Mutex::new(ExtractSettings::default())),
+        timeouts: Arc::new(Mutex::new(TimeoutSettings::default())),
+        redirects: Arc::new(Mutex::new(RedirectSettings::default())),
+        webhooks: Arc::new(Mutex::new(Vec::new())),
+        notifications: Arc::new(Mutex::new(NotificationSettings::default())),
+        clipboard: Arc::new(Mutex::new(ClipboardSettings::default())),
+        resolvers: Arc::new(ResolverRegistry::default()),
+        resolver_settings: Arc::new(Mutex::new(ResolverSettings::default())),
+        scripts: Arc::new(ScriptHost::default()),
+        script_settings: Arc::new(Mutex::new(ScriptSettings::default())),
+        feeds: Arc::new(Mutex::new(Vec::new())),
+        feed_interval_minutes: Arc::new(Mutex::new(feeds::default_interval_minutes())),
+        watch_folders: Arc::new(Mutex::new(Vec::new())),
+        events: EventBus::default(),
     };
 
     
@@ -1329,12 +3136,12 @@ fn main() {
         
         
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_notification::init())
         .manage(app_state.clone())
         .setup(|app| {
-            if let Err(e) = ensure_state_file_exists() {
-                eprintln!("Failed to create default state.json: {}", e);
-            }
+            app.manage(open_store(app.handle()));
+            spawn_change_tracker(app.handle());
             let handle_owned = app.handle().clone();
 
             let state_owned = app.state::<AppState>().inner().clone();
@@ -1342,6 +3149,17 @@ fn main() {
             tauri::async_runtime::spawn(async move {
                 load_state(handle_owned, state_owned.clone()).await;
             });
+            events::spawn_tauri_forwarder(app.handle().clone(), &app.state::<AppState>().events);
+            disk_space::spawn_disk_space_watcher(app.handle().clone());
+            if let Err(e) = scripting::load_scripts(app.handle()) {
+                eprintln!("Failed to load scripts: {}", e);
+            }
+            webhooks::spawn_webhook_dispatcher(app.handle().clone());
+            notifications::spawn_notifier(app.handle().clone());
+            clipboard::spawn_clipboard_watcher(app.handle().clone());
+            feeds::spawn_feed_poller(app.handle().clone());
+            watch::spawn_folder_watcher(app.handle().clone());
+            post_actions::spawn_expiry_sweeper(app.handle().clone());
 
             Ok(())
         })
@@ -1366,8 +3184,132 @@ fn main() {
             get_download_dir,
             set_download_dir,
             set_max_retries,
-            get_max_retries
+            get_max_retries,
+            get_conflict_policy,
+            set_conflict_policy,
+            get_min_free_space_mb,
+            set_min_free_space_mb,
+            get_preallocate,
+            set_preallocate,
+            get_statistics,
+            set_download_category,
+            get_post_actions,
+            set_post_actions,
+            set_download_post_actions,
+            get_extract_settings,
+            set_extract_settings,
+            set_download_extract,
+            set_download_mirrors,
+            enqueue_metalink,
+            refresh_download_url,
+            get_retry_policy,
+            set_retry_policy,
+            set_download_retry_policy,
+            get_download_error,
+            get_timeouts,
+            set_timeouts,
+            get_redirect_settings,
+            set_redirect_settings,
+            get_progress_interval_ms,
+            set_progress_interval_ms,
+            get_webhooks,
+            set_webhooks,
+            test_webhook,
+            get_notification_settings,
+            set_notification_settings,
+            get_clipboard_settings,
+            set_clipboard_settings,
+            list_resolvers,
+            get_resolver_settings,
+            set_resolver_settings,
+            resolve_url,
+            get_script_settings,
+            set_script_settings,
+            list_scripts,
+            reload_scripts,
+            get_scripts_dir,
+            open_scripts_dir,
+            crawl_url,
+            enqueue_crawled,
+            list_feeds,
+            add_feed,
+            update_feed,
+            remove_feed,
+            check_feed,
+            get_feed_interval,
+            set_feed_interval,
+            get_watch_folders,
+            set_watch_folders,
+            export_downloads,
+            import_downloads,
+            export_settings,
+            import_settings
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty folder of its own under the system temp directory.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mad_byte-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn download_at(id: u64, path: &Path) -> Download {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "url": "https://example.com/file",
+            "file_path": path,
+            "progress": 0.0,
+            "status": "queued",
+            "downloaded_bytes": 0,
+            "speed_kbps": 0.0,
+            "retries_left": 0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn file_conflicts_with_files_parts_and_downloads() {
+        let dir = scratch_dir("conflicts");
+        let on_disk = dir.join("on-disk.zip");
+        fs::write(&on_disk, b"x").unwrap();
+        let partial = dir.join("partial.zip");
+        fs::write(part_path(&partial), b"x").unwrap();
+        let claimed = dir.join("claimed.zip");
+        let downloads = vec![download_at(1, &claimed)];
+
+        assert!(file_conflicts(&on_disk, &downloads));
+        assert!(file_conflicts(&partial, &downloads));
+        assert!(file_conflicts(&claimed, &downloads));
+        assert!(!file_conflicts(&dir.join("free.zip"), &downloads));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unique_file_path_skips_taken_names() {
+        let dir = scratch_dir("unique");
+        let path = dir.join("report.pdf");
+        fs::write(&path, b"x").unwrap();
+        fs::write(dir.join("report (1).pdf"), b"x").unwrap();
+        let downloads = vec![download_at(1, &dir.join("report (2).pdf"))];
+
+        assert_eq!(unique_file_path(&path, &downloads), dir.join("report (3).pdf"));
+        assert_eq!(unique_file_path(&dir.join("README"), &[]), dir.join("README (1)"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn file_name_only_drops_folders() {
+        assert_eq!(file_name_only("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(file_name_only(" movie.mkv ").as_deref(), Some("movie.mkv"));
+        assert_eq!(file_name_only(".."), None);
+        assert_eq!(file_name_only(""), None);
+    }
+}
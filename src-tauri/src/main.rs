@@ -1,7 +1,10 @@
-// Prevents additional console window on Windows in release, DO NOT REMOVE!!
-#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-// #[cfg(mobile)]
-// #[tauri::mobile_entry_point]
-fn main() {
-    mad_byte_lib::run()
-}
+// Prevents additional console window on Windows in release, DO NOT REMOVE!!
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+// #[cfg(mobile)]
+// #[tauri::mobile_entry_point]
+fn main() {
+    if mad_byte_lib::native_host::requested() {
+        std::process::exit(mad_byte_lib::native_host::run());
+    }
+    mad_byte_lib::run()
+}
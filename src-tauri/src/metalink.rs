@@ -0,0 +1,128 @@
+use roxmltree::{Document, Node};
+
+/// One file described by a Metalink document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetalinkFile {
+    pub name: String,
+    pub size: Option<u64>,
+    /// HTTP(S) sources, best first.
+    pub urls: Vec<String>,
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+/// Parses Metalink 4 (RFC 5854) and Metalink 3 documents. Sources are
+/// ordered by `priority` (v4, lower first) or `preference` (v3, higher
+/// first); non-HTTP sources such as torrents are dropped.
+pub fn parse(xml: &str) -> Result<Vec<MetalinkFile>, String> {
+    let doc = Document::parse(xml).map_err(|e| format!("Invalid Metalink: {}", e))?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "metalink" {
+        return Err("Not a Metalink document".to_string());
+    }
+    // v3 nests files under <files>, v4 puts them directly under <metalink>.
+    let container = child(root, "files").unwrap_or(root);
+
+    let mut files = Vec::new();
+    for file in container.children().filter(|n| n.tag_name().name() == "file") {
+        let Some(name) = file.attribute("name") else {
+            continue;
+        };
+        let size = child(file, "size")
+            .and_then(|n| n.text())
+            .and_then(|t| t.trim().parse().ok());
+
+        let url_nodes: Vec<Node> = match child(file, "resources") {
+            Some(resources) => resources.children().collect(),
+            None => file.children().collect(),
+        };
+        let mut urls: Vec<(i64, String)> = url_nodes
+            .into_iter()
+            .filter(|n| n.tag_name().name() == "url")
+            .filter_map(|n| {
+                let url = n.text()?.trim().to_string();
+                if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    return None;
+                }
+                let rank = if let Some(priority) = n.attribute("priority") {
+                    priority.parse().unwrap_or(i64::MAX)
+                } else if let Some(preference) = n.attribute("preference") {
+                    -preference.parse().unwrap_or(0)
+                } else {
+                    i64::MAX
+                };
+                Some((rank, url))
+            })
+            .collect();
+        urls.sort_by_key(|(rank, _)| *rank);
+
+        if urls.is_empty() {
+            continue;
+        }
+        files.push(MetalinkFile {
+            // Only keep the last path component so a document can't write
+            // outside the download directory.
+            name: name.rsplit(['/', '\\']).next().unwrap_or(name).to_string(),
+            size,
+            urls: urls.into_iter().map(|(_, url)| url).collect(),
+        });
+    }
+
+    if files.is_empty() {
+        return Err("Metalink lists no downloadable files".to_string());
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_metalink_4_by_priority() {
+        let xml = r#"<?xml version="1.0"?>
+            <metalink xmlns="urn:ietf:params:xml:ns:metalink">
+              <file name="../iso/debian.iso">
+                <size>1024</size>
+                <url priority="2">https://mirror-b.example/debian.iso</url>
+                <url priority="1">https://mirror-a.example/debian.iso</url>
+                <metaurl mediatype="torrent">https://example.com/debian.torrent</metaurl>
+                <url>ftp://mirror-c.example/debian.iso</url>
+              </file>
+              <file name="no-sources.txt"><url>ftp://example.com/x</url></file>
+            </metalink>"#;
+        assert_eq!(
+            parse(xml).unwrap(),
+            vec![MetalinkFile {
+                name: "debian.iso".to_string(),
+                size: Some(1024),
+                urls: vec![
+                    "https://mirror-a.example/debian.iso".to_string(),
+                    "https://mirror-b.example/debian.iso".to_string(),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_metalink_3_by_preference() {
+        let xml = r#"<metalink version="3.0" xmlns="http://www.metalinker.org/">
+              <files><file name="a.zip"><resources>
+                <url type="http" preference="10">http://slow.example/a.zip</url>
+                <url type="http" preference="90">http://fast.example/a.zip</url>
+              </resources></file></files>
+            </metalink>"#;
+        let files = parse(xml).unwrap();
+        assert_eq!(files[0].size, None);
+        assert_eq!(files[0].urls, vec!["http://fast.example/a.zip", "http://slow.example/a.zip"]);
+    }
+
+    #[test]
+    fn parse_rejects_documents_without_files() {
+        assert!(parse("<metalink/>").is_err());
+        assert!(parse("<feed/>").is_err());
+        assert!(parse("<metalink>").is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::headers::RequestHeaders;
+use crate::{file_name_only, HTTP_API_PORT};
+
+/// Chrome caps messages to the host at 64 MiB; anything larger is garbage.
+const MAX_MESSAGE_LEN: u32 = 64 * 1024 * 1024;
+/// How long to wait for a freshly started app to open its HTTP API.
+const STARTUP_ATTEMPTS: u32 = 20;
+const STARTUP_POLL: Duration = Duration::from_millis(500);
+
+/// What the extension sends. `type` is `add` (the default) or `ping`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct HostMessage {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    url: String,
+    /// The name the browser suggested for the file.
+    #[serde(alias = "suggested_filename", alias = "save_as")]
+    filename: Option<String>,
+    #[serde(flatten)]
+    headers: RequestHeaders,
+}
+
+/// Body of `POST /add`, see `EnqueueOptions`.
+#[derive(Serialize)]
+struct AddRequest<'a> {
+    url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    save_as: Option<String>,
+    #[serde(flatten)]
+    headers: &'a RequestHeaders,
+}
+
+/// Whether the process was started by a browser as a native-messaging host:
+/// Chrome passes the `chrome-extension://` origin, Firefox the manifest path
+/// and extension id. `--native-messaging-host` forces it for testing.
+pub fn requested() -> bool {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    args.iter()
+        .any(|a| a == "--native-messaging-host" || a.starts_with("chrome-extension://"))
+        || (args.len() >= 2 && args[0].ends_with(".json"))
+}
+
+fn read_message(input: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match input.read_exact(&mut len) {
+        Ok(()) => {}
+        // The browser closed the port.
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_ne_bytes(len);
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Message of {} bytes is too large", len),
+        ));
+    }
+    let mut body = vec![0u8; len as usize];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = serde_json::to_vec(message)?;
+    output.write_all(&(body.len() as u32).to_ne_bytes())?;
+    output.write_all(&body)?;
+    output.flush()
+}
+
+/// Starts the app in the background, without host-mode arguments. It must
+/// not inherit the browser's pipes: its output would corrupt the message
+/// channel and the open handles would keep the port alive.
+fn launch_app() -> io::Result<()> {
+    Command::new(std::env::current_exe()?)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+async fn forward(client: &reqwest::Client, message: &HostMessage) -> Result<Value, String> {
+    let request = AddRequest {
+        url: &message.url,
+        save_as: message.filename.as_deref().and_then(file_name_only),
+        headers: &message.headers,
+    };
+    let endpoint = format!("http://127.0.0.1:{}/add", HTTP_API_PORT);
+
+    let mut launched = false;
+    let mut attempts = 0;
+    loop {
+        match client.post(&endpoint).json(&request).send().await {
+            Ok(resp) => return resp.json::<Value>().await.map_err(|e| e.to_string()),
+            Err(e) if e.is_connect() && attempts < STARTUP_ATTEMPTS => {
+                if !launched {
+                    launch_app().map_err(|e| format!("Failed to start MadByte: {}", e))?;
+                    launched = true;
+                }
+                attempts += 1;
+                tokio::time::sleep(STARTUP_POLL).await;
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+async fn handle(client: &reqwest::Client, body: &[u8]) -> Value {
+    let message: HostMessage = match serde_json::from_slice(body) {
+        Ok(message) => message,
+        Err(e) => return json!({ "status": "error", "message": format!("Invalid message: {}", e) }),
+    };
+    match message.kind.as_deref().unwrap_or("add") {
+        "ping" => json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }),
+        "add" if message.url.trim().is_empty() => {
+            json!({ "status": "error", "message": "Missing url" })
+        }
+        "add" => forward(client, &message)
+            .await
+            .unwrap_or_else(|e| json!({ "status": "error", "message": e })),
+        other => json!({ "status": "error", "message": format!("Unknown message type: {}", other) }),
+    }
+}
+
+/// Serves native messages until the browser closes stdin and returns the
+/// exit code. Each message is a native-endian `u32` length followed by that
+/// many bytes of UTF-8 JSON, in both directions. Links are forwarded to the
+/// running app's HTTP API, starting the app first if it isn't up.
+pub fn run() -> i32 {
+    let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start the native-messaging host: {}", e);
+            return 1;
+        }
+    };
+    let client = reqwest::Client::new();
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+
+    loop {
+        let body = match read_message(&mut stdin) {
+            Ok(Some(body)) => body,
+            Ok(None) => return 0,
+            Err(e) => {
+                eprintln!("Native messaging: {}", e);
+                return 1;
+            }
+        };
+        let reply = runtime.block_on(handle(&client, &body));
+        if let Err(e) = write_message(&mut stdout, &reply) {
+            eprintln!("Native messaging: {}", e);
+            return 1;
+        }
+    }
+}
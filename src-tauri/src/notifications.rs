@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::events::{DownloadEvent, QueueEmptyWatch};
+use crate::{AppState, Download, DownloadStatus};
+
+/// Which events raise a desktop notification.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub on_completed: bool,
+    pub on_failed: bool,
+    pub on_queue_empty: bool,
+    /// Clicking a notification reveals the file (or the download folder)
+    /// in the file manager. Only Linux reports clicks back to us.
+    pub open_folder_on_click: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            on_completed: true,
+            on_failed: true,
+            on_queue_empty: false,
+            open_folder_on_click: true,
+        }
+    }
+}
+
+fn file_name(download: &Download) -> String {
+    download
+        .file_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Unknown file".to_string())
+}
+
+fn failure_reason(download: &Download) -> String {
+    match (&download.error, &download.status) {
+        (Some(error), _) => error.message.clone(),
+        (None, DownloadStatus::Failed(reason)) => reason.clone(),
+        _ => String::new(),
+    }
+}
+
+/// Shows a notification; with `reveal` set, clicking it opens that folder
+/// or reveals that file.
+#[cfg(target_os = "linux")]
+fn show(_app_handle: &AppHandle, title: String, body: String, reveal: Option<PathBuf>) {
+    tokio::task::spawn_blocking(move || {
+        let mut notification = notify_rust::Notification::new();
+        notification.summary(&title).body(&body).auto_icon();
+        if reveal.is_some() {
+            notification.action("default", "Show in folder");
+        }
+        let handle = match notification.show() {
+            Ok(handle) => handle,
+            Err(e) => {
+                eprintln!("Failed to show notification: {}", e);
+                return;
+            }
+        };
+        if let Some(path) = reveal {
+            handle.wait_for_action(|action| {
+                if action != "default" {
+                    return;
+                }
+                let result = if path.is_dir() {
+                    tauri_plugin_opener::open_path(&path, None::<&str>)
+                } else {
+                    tauri_plugin_opener::reveal_item_in_dir(&path)
+                };
+                if let Err(e) = result {
+                    eprintln!("Failed to open {}: {}", path.display(), e);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(not(target_os = "linux"))]
+fn show(app_handle: &AppHandle, title: String, body: String, _reveal: Option<PathBuf>) {
+    use tauri_plugin_notification::NotificationExt;
+
+    let result = app_handle
+        .notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show();
+    if let Err(e) = result {
+        eprintln!("Failed to show notification: {}", e);
+    }
+}
+
+/// Raises notifications for finished and failed downloads and for the queue
+/// running empty, as enabled in `NotificationSettings`.
+pub fn spawn_notifier(app_handle: AppHandle) {
+    let state = app_handle.state::<AppState>().inner().clone();
+    let mut events = Box::pin(state.events.stream());
+    tokio::spawn(async move {
+        let mut queue_watch = QueueEmptyWatch::default();
+        while let Some(event) = events.next().await {
+            let settings = state.notifications.lock().await.clone();
+            let click = |path: PathBuf| settings.open_folder_on_click.then_some(path);
+            match &event {
+                DownloadEvent::Completed { download } if settings.on_completed => {
+                    show(
+                        &app_handle,
+                        "Download Complete".to_string(),
+                        format!("{} has finished downloading.", file_name(download)),
+                        click(download.file_path.clone()),
+                    );
+                }
+                DownloadEvent::Failed { download } if settings.on_failed => {
+                    show(
+                        &app_handle,
+                        "Download Failed".to_string(),
+                        format!("{} failed: {}", file_name(download), failure_reason(download)),
+                        None,
+                    );
+                }
+                _ => {}
+            }
+
+            if queue_watch.observe(&event, &state).await && settings.on_queue_empty {
+                let dir = state.download_dir.lock().await.clone();
+                show(
+                    &app_handle,
+                    "All Downloads Finished".to_string(),
+                    "Nothing is left in the queue.".to_string(),
+                    click(dir),
+                );
+            }
+        }
+    });
+}
@@ -1,24 +1,100 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 use std::collections::{HashSet, VecDeque};
 use std::env::current_dir;
-use std::fs::File;
+use std::hash::{BuildHasher, RandomState};
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tauri::AppHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
 use tokio::fs;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::Mutex;
 
-use crate::{AppState, Download, DownloadStatus};
+use crate::store::Store;
+use crate::clipboard::ClipboardSettings;
+use crate::extract::ExtractSettings;
+use crate::feeds::{self, Feed};
+use crate::notifications::NotificationSettings;
+use crate::post_actions::PostAction;
+use crate::redirects::RedirectSettings;
+use crate::resolvers::ResolverSettings;
+use crate::retry::RetryPolicy;
+use crate::scripting::ScriptSettings;
+use crate::timeouts::TimeoutSettings;
+use crate::watch::WatchFolder;
+use crate::webhooks::Webhook;
+use crate::{AppState, ConflictPolicy, Download, DownloadStatus};
+
+/// Bump this and add a step to `migrate` whenever the layout of
+/// `PersistedState` changes in a way `#[serde(default)]` can't absorb.
+pub const SCHEMA_VERSION: u32 = 1;
+
+const STATE_FILE: &str = "state.json";
+const DB_FILE: &str = "madbyte.db";
+const API_TOKEN_FILE: &str = "api-token";
+
+static SAVE_LOCK: Mutex<()> = Mutex::const_new(());
 
 #[derive(Serialize, Deserialize)]
 pub struct PersistedState {
+    #[serde(default)]
+    pub schema_version: u32,
     pub downloads: Vec<Download>,
     pub queue: VecDeque<Download>,
     pub max_concurrent_downloads: usize, 
     pub speed_limit: Option<f64>,        
     pub download_dir: PathBuf,
     pub max_retries: u32,
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    #[serde(default = "default_min_free_space_mb")]
+    pub min_free_space_mb: u64,
+    #[serde(default)]
+    pub preallocate: bool,
+    #[serde(default)]
+    pub post_actions: Vec<PostAction>,
+    #[serde(default)]
+    pub extract_settings: ExtractSettings,
+    /// Absent in older state, where only `max_retries` was stored.
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    #[serde(default)]
+    pub timeouts: TimeoutSettings,
+    #[serde(default)]
+    pub redirects: RedirectSettings,
+    #[serde(default = "default_progress_interval_ms")]
+    pub progress_interval_ms: u64,
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    #[serde(default)]
+    pub clipboard: ClipboardSettings,
+    #[serde(default)]
+    pub resolvers: ResolverSettings,
+    #[serde(default)]
+    pub scripts: ScriptSettings,
+    #[serde(default)]
+    pub feeds: Vec<Feed>,
+    #[serde(default = "feeds::default_interval_minutes")]
+    pub feed_interval_minutes: u64,
+    #[serde(default)]
+    pub watch_folders: Vec<WatchFolder>,
+}
+
+pub fn default_min_free_space_mb() -> u64 {
+    200
+}
+
+pub fn default_progress_interval_ms() -> u64 {
+    250
+}
+
+fn default_download_dir() -> PathBuf {
+    dirs::download_dir().unwrap_or_else(|| PathBuf::from("./downloads"))
 }
 
 pub async fn deduplicate_state_lists(state: &AppState) {
@@ -40,101 +116,438 @@ pub async fn deduplicate_state_lists(state: &AppState) {
         drop(downloads);
     }
 }
-pub fn get_state_path() -> PathBuf {
-    current_dir().unwrap().join("state.json")
-}
-pub fn ensure_state_file_exists() -> std::io::Result<()> {
-    let path = get_state_path();
-
-    if !path.exists() {
-        let download_dir = dirs::download_dir()
-            .unwrap_or_else(|| PathBuf::from("./downloads")) 
-            .to_string_lossy()
-            .to_string();
-
-        let default_state = format!(
-            r#"{{
-                "downloads": [],
-                "queue": [],
-                "max_concurrent_downloads": 3,
-                "speed_limit": 0,
-                "download_dir": "{}"
-                "max_retries": 10
-            }}"#,
-            download_dir.replace('\\', "\\\\"), 
-        );
-
-        let mut file = File::create(&path)?;
-        file.write_all(default_state.trim().as_bytes())?;
+/// Persisted files live in the platform app-data directory so they don't
+/// depend on the working directory the app was launched from.
+fn data_dir(app: &AppHandle) -> PathBuf {
+    match app.path().app_data_dir() {
+        Ok(dir) => {
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                eprintln!("Failed to create {}: {}", dir.display(), e);
+            }
+            dir
+        }
+        Err(e) => {
+            eprintln!("No app data directory ({}), using the working directory", e);
+            current_dir().unwrap_or_default()
+        }
     }
+}
 
-    Ok(())
+pub fn get_state_path(app: &AppHandle) -> PathBuf {
+    data_dir(app).join(STATE_FILE)
 }
 
-pub async fn save_state(app: &AppHandle, state: &AppState) {
-    deduplicate_state_lists(&state).await;
-    println!(" save0");
-    let path = get_state_path();
-    println!(" save1");
-
-    let downloads = state.downloads.lock().await.clone();
-    println!(" save2");
-    let queue = state.queue.lock().await.clone();
-    let max_concurrent_downloads = state.max_concurrent_downloads.lock().await.clone();
-    let speed_limit = state.speed_limit.lock().await.clone();
+/// The token the HTTP API wants for requests that change settings, kept in
+/// `api-token` next to the state. Created on first use and, on Unix, only
+/// readable by the user: a web page can reach the API but not read the file.
+pub fn api_token(app: &AppHandle) -> Result<String, String> {
+    let path = data_dir(app).join(API_TOKEN_FILE);
+    if let Ok(token) = std::fs::read_to_string(&path) {
+        if !token.trim().is_empty() {
+            return Ok(token.trim().to_string());
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    for _ in 0..4 {
+        hasher.update(RandomState::new().hash_one(SystemTime::now()).to_le_bytes());
+    }
+    let token: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(token.as_bytes()))
+        .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    Ok(token)
+}
+
+/// Opens the SQLite store, falling back to an in-memory database (nothing
+/// persisted, but the app keeps working) if the file can't be opened.
+pub fn open_store(app: &AppHandle) -> Arc<Store> {
+    let path = data_dir(app).join(DB_FILE);
+    let store = match Store::open(&path) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("Failed to open {}: {}", path.display(), e);
+            Store::open_in_memory().expect("failed to open in-memory database")
+        }
+    };
+    Arc::new(store)
+}
+
+fn managed_store(app: &AppHandle) -> Option<Arc<Store>> {
+    app.try_state::<Arc<Store>>().map(|store| store.inner().clone())
+}
+
+/// Marks the downloads the event bus reports on as changed, so a save only
+/// writes those. Must run before anything is enqueued.
+pub fn spawn_change_tracker(app: &AppHandle) {
+    let Some(store) = managed_store(app) else {
+        return;
+    };
+    let mut events = app.state::<AppState>().events.subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if let Some(id) = event.download_id() {
+                        store.mark_changed(id);
+                    }
+                }
+                Err(RecvError::Lagged(_)) => store.mark_all_changed(),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// `path` with a `.corrupt-<unix time>` suffix, for keeping a file that
+/// failed to load instead of overwriting it.
+fn corrupt_path(path: &Path) -> PathBuf {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    with_suffix(path, &format!(".corrupt-{}", stamp))
+}
+
+fn legacy_state_path() -> PathBuf {
+    current_dir().unwrap_or_default().join(STATE_FILE)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Upgrades an older state file one schema version at a time.
+fn migrate(mut value: Value) -> Result<Value, String> {
+    let obj = value
+        .as_object_mut()
+        .ok_or_else(|| "state file is not a JSON object".to_string())?;
+    let mut version = obj
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as u32;
+
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "state file has schema version {}, this build only understands up to {}",
+            version, SCHEMA_VERSION
+        ));
+    }
+
+    while version < SCHEMA_VERSION {
+        if version == 0 {
+            // Unversioned files came from the old hand-written template and
+            // earlier builds, which could be missing any of these.
+            obj.entry("downloads").or_insert(json!([]));
+            obj.entry("queue").or_insert(json!([]));
+            obj.entry("max_concurrent_downloads").or_insert(json!(3));
+            obj.entry("speed_limit").or_insert(json!(0.0));
+            obj.entry("download_dir").or_insert(json!(default_download_dir()));
+            obj.entry("max_retries").or_insert(json!(10));
+        }
+        version += 1;
+    }
+
+    obj.insert("schema_version".to_string(), json!(SCHEMA_VERSION));
+    Ok(value)
+}
+
+async fn read_state_file(path: &Path) -> Result<PersistedState, String> {
+    let contents = fs::read_to_string(path).await.map_err(|e| e.to_string())?;
+    let value: Value = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    serde_json::from_value(migrate(value)?).map_err(|e| e.to_string())
+}
+
+/// Everything `save_state` writes, as it is in memory right now.
+pub async fn snapshot(state: &AppState) -> PersistedState {
+    let mut persisted = settings_snapshot(state).await;
+    persisted.downloads = state.downloads.lock().await.clone();
+    persisted.queue = state.queue.lock().await.clone();
+    persisted
+}
+
+/// `snapshot` with the downloads and queue left empty.
+async fn settings_snapshot(state: &AppState) -> PersistedState {
+    let max_concurrent_downloads = *state.max_concurrent_downloads.lock().await;
+    let speed_limit = *state.speed_limit.lock().await;
     let download_dir = state.download_dir.lock().await.clone();
-    let max_retries = state.max_retries.lock().await.clone();
-    println!(" save3");
+    let retry_policy = state.retry_policy.lock().await.clone();
+    let max_retries = retry_policy.max_attempts;
+    let conflict_policy = *state.conflict_policy.lock().await;
+    let min_free_space_mb = *state.min_free_space_mb.lock().await;
+    let preallocate = *state.preallocate.lock().await;
+    let post_actions = state.post_actions.lock().await.clone();
+    let extract_settings = state.extract_settings.lock().await.clone();
+    let timeouts = state.timeouts.lock().await.clone();
+    let redirects = state.redirects.lock().await.clone();
+    let progress_interval_ms = state.events.progress_interval().as_millis() as u64;
+    let webhooks = state.webhooks.lock().await.clone();
+    let notifications = state.notifications.lock().await.clone();
+    let clipboard = state.clipboard.lock().await.clone();
+    let resolvers = state.resolver_settings.lock().await.clone();
+    let scripts = state.script_settings.lock().await.clone();
+    let feeds = state.feeds.lock().await.clone();
+    let feed_interval_minutes = *state.feed_interval_minutes.lock().await;
+    let watch_folders = state.watch_folders.lock().await.clone();
 
-    let persisted = PersistedState {
-        downloads,
-        queue,
+    PersistedState {
+        schema_version: SCHEMA_VERSION,
+        downloads: Vec::new(),
+        queue: VecDeque::new(),
         max_concurrent_downloads,
         speed_limit,
         download_dir,
         max_retries,
+        conflict_policy,
+        min_free_space_mb,
+        preallocate,
+        post_actions,
+        extract_settings,
+        retry_policy: Some(retry_policy),
+        timeouts,
+        redirects,
+        progress_interval_ms,
+        webhooks,
+        notifications,
+        clipboard,
+        resolvers,
+        scripts,
+        feeds,
+        feed_interval_minutes,
+        watch_folders,
+    }
+}
+
+/// A download as it is stored, including the cookies its serialization
+/// leaves out (see `headers::without_cookies`).
+fn stored_download(download: &Download) -> Result<Value, serde_json::Error> {
+    let mut value = serde_json::to_value(download)?;
+    if let (Some(cookies), Some(headers)) = (
+        &download.headers.cookies,
+        value.get_mut("headers").and_then(Value::as_object_mut),
+    ) {
+        headers.insert("cookies".to_string(), json!(cookies));
+    }
+    Ok(value)
+}
+
+pub async fn save_state(app: &AppHandle, state: &AppState) {
+    deduplicate_state_lists(state).await;
+    let Some(store) = managed_store(app) else {
+        return;
+    };
+    let _guard = SAVE_LOCK.lock().await;
+
+    let settings = match serde_json::to_value(settings_snapshot(state).await) {
+        Ok(Value::Object(mut settings)) => {
+            settings.remove("downloads");
+            settings.remove("queue");
+            settings
+        }
+        Ok(_) => return,
+        Err(e) => {
+            eprintln!("Failed to serialize state: {}", e);
+            return;
+        }
     };
-    if let Ok(json) = serde_json::to_string_pretty(&persisted) {
-        let _ = fs::write(path, json).await;
-    } else if let Err(e) = serde_json::to_string_pretty(&persisted) {
-        println!("w asdafsdf save, {}", e.to_string());
+    let (ids, changed) = {
+        let downloads = state.downloads.lock().await;
+        let ids: Vec<u64> = downloads.iter().map(|d| d.id).collect();
+        let needed = store.take_changed(&ids);
+        let changed: Vec<Download> = downloads
+            .iter()
+            .filter(|d| needed.as_ref().is_none_or(|needed| needed.contains(&d.id)))
+            .cloned()
+            .collect();
+        (ids, changed)
+    };
+    let queue: Vec<u64> = state.queue.lock().await.iter().map(|d| d.id).collect();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let rows = changed
+            .iter()
+            .map(|d| stored_download(d).map(|value| (d.id, value.to_string())))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        store.write(settings, ids, rows, queue)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|result| result);
+    if let Err(e) = result {
+        eprintln!("Failed to save state: {}", e);
     }
 }
 
-pub async fn load_state(app: AppHandle, state: AppState) {
-    
-    let path = get_state_path(); 
-    println!("{}", path.to_str().unwrap());
-    if let Ok(contents) = fs::read_to_string(path.clone()).await {
-        if let Ok(mut loaded) = serde_json::from_str::<PersistedState>(&contents) {
-            for dl in loaded.downloads.iter_mut() {
-                if dl.status != DownloadStatus::Completed {
-                    dl.status = DownloadStatus::Paused;
-                    dl.speed_kbps = 0.0;
-                    dl.eta_seconds = None;
+/// Copies an existing state.json (from the app-data directory, or the working
+/// directory older builds used) into the empty store, then renames it so the
+/// import only ever happens once.
+async fn import_state_json(app: &AppHandle, store: &Arc<Store>) {
+    let candidates = [get_state_path(app), legacy_state_path()];
+    let Some(path) = candidates.iter().find(|p| p.exists()) else {
+        return;
+    };
+
+    let loaded = match read_state_file(path).await {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Failed to load {}: {}", path.display(), e);
+            let backup = with_suffix(path, ".bak");
+            // Keep the unreadable file around rather than importing it again.
+            let _ = fs::rename(path, corrupt_path(path)).await;
+            match read_state_file(&backup).await {
+                Ok(loaded) => {
+                    eprintln!("Restored state from backup");
+                    loaded
                 }
+                Err(_) => return,
             }
-            for dl in loaded.queue.iter_mut() {
-                if dl.status != DownloadStatus::Completed {
-                    dl.status = DownloadStatus::Paused;
-                    dl.speed_kbps = 0.0;
-                    dl.eta_seconds = None;
-                }
+        }
+    };
+
+    let store = store.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut value = serde_json::to_value(&loaded).map_err(|e| e.to_string())?;
+        let downloads = loaded
+            .downloads
+            .iter()
+            .map(stored_download)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        value["downloads"] = Value::Array(downloads);
+        store.sync(value)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|result| result);
+    match result {
+        Ok(()) => {
+            let _ = fs::rename(path, with_suffix(path, ".imported")).await;
+            eprintln!("Imported {} into the database", path.display());
+        }
+        Err(e) => eprintln!("Failed to import {}: {}", path.display(), e),
+    }
+}
+
+pub async fn load_state(app: AppHandle, state: AppState) {
+    let Some(store) = managed_store(&app) else {
+        return;
+    };
+    let is_empty = {
+        let store = store.clone();
+        tokio::task::spawn_blocking(move || store.is_empty()).await
+    };
+    if matches!(is_empty, Ok(Ok(true))) {
+        import_state_json(&app, &store).await;
+    }
+
+    let loaded = {
+        let store = store.clone();
+        tokio::task::spawn_blocking(move || store.load())
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|result| result)
+    };
+    let loaded = loaded
+        .and_then(migrate)
+        .and_then(|value| serde_json::from_value::<PersistedState>(value).map_err(|e| e.to_string()));
+    let loaded = match loaded {
+        Ok(loaded) => Some(loaded),
+        Err(e) => {
+            eprintln!("Failed to load state from the database: {}", e);
+            // Saving starts from scratch now; keep a copy of what was there.
+            let backup = corrupt_path(&data_dir(&app).join(DB_FILE));
+            let result = tokio::task::spawn_blocking(move || store.backup_to(&backup)).await;
+            if let Ok(Err(e)) = result {
+                eprintln!("Failed to back up the database: {}", e);
+            }
+            None
+        }
+    };
+
+    if let Some(mut loaded) = loaded {
+        for dl in loaded.downloads.iter_mut() {
+            if dl.status != DownloadStatus::Completed {
+                dl.status = DownloadStatus::Paused;
+                dl.speed_kbps = 0.0;
+                dl.eta_seconds = None;
+                dl.next_retry_at = None;
+            }
+        }
+        for dl in loaded.queue.iter_mut() {
+            if dl.status != DownloadStatus::Completed {
+                dl.status = DownloadStatus::Paused;
+                dl.speed_kbps = 0.0;
+                dl.eta_seconds = None;
+                dl.next_retry_at = None;
             }
-            
-            println!("asdafsdf");
-            *state.downloads.lock().await = loaded.downloads;
-            *state.queue.lock().await = loaded.queue;
-            *state.speed_limit.lock().await = loaded.speed_limit;
-            *state.download_dir.lock().await = loaded.download_dir;
-            *state.max_concurrent_downloads.lock().await = loaded.max_concurrent_downloads;
-            *state.max_retries.lock().await = loaded.max_retries;
-        } else if let Err(e) = serde_json::from_str::<PersistedState>(&contents) {
-            println!("{}", e.to_string());
-            println!("w 1 asdafsdf");
         }
-    } else if let Err(e) = fs::read_to_string(path).await {
-        println!("w{} 2 ", e.to_string());
+
+        *state.downloads.lock().await = std::mem::take(&mut loaded.downloads);
+        *state.queue.lock().await = std::mem::take(&mut loaded.queue);
+        apply_settings(&state, loaded).await;
     }
     deduplicate_state_lists(&state).await;
 }
+
+/// Puts every setting of `loaded` into effect; its downloads and queue are
+/// left alone.
+pub async fn apply_settings(state: &AppState, loaded: PersistedState) {
+    *state.speed_limit.lock().await = loaded.speed_limit;
+    *state.download_dir.lock().await = loaded.download_dir;
+    *state.max_concurrent_downloads.lock().await = loaded.max_concurrent_downloads;
+    *state.retry_policy.lock().await = loaded.retry_policy.unwrap_or(RetryPolicy {
+        max_attempts: loaded.max_retries,
+        ..RetryPolicy::default()
+    });
+    *state.conflict_policy.lock().await = loaded.conflict_policy;
+    *state.min_free_space_mb.lock().await = loaded.min_free_space_mb;
+    *state.preallocate.lock().await = loaded.preallocate;
+    *state.post_actions.lock().await = loaded.post_actions;
+    *state.extract_settings.lock().await = loaded.extract_settings;
+    *state.timeouts.lock().await = loaded.timeouts;
+    *state.redirects.lock().await = loaded.redirects;
+    *state.webhooks.lock().await = loaded.webhooks;
+    *state.notifications.lock().await = loaded.notifications;
+    *state.clipboard.lock().await = loaded.clipboard;
+    *state.resolver_settings.lock().await = loaded.resolvers;
+    *state.script_settings.lock().await = loaded.scripts;
+    *state.feeds.lock().await = loaded.feeds;
+    *state.feed_interval_minutes.lock().await = loaded.feed_interval_minutes;
+    *state.watch_folders.lock().await = loaded.watch_folders;
+    state
+        .events
+        .set_progress_interval(Duration::from_millis(loaded.progress_interval_ms));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_fills_in_unversioned_files() {
+        let migrated = migrate(json!({ "speed_limit": 50.0, "downloads": [] })).unwrap();
+        assert_eq!(migrated["schema_version"], json!(SCHEMA_VERSION));
+        assert_eq!(migrated["speed_limit"], json!(50.0));
+        assert_eq!(migrated["queue"], json!([]));
+        assert_eq!(migrated["max_concurrent_downloads"], json!(3));
+        assert_eq!(migrated["max_retries"], json!(10));
+        assert!(serde_json::from_value::<PersistedState>(migrated).is_ok());
+    }
+
+    #[test]
+    fn migrate_keeps_current_files_and_refuses_newer_ones() {
+        let current = json!({ "schema_version": SCHEMA_VERSION, "max_retries": 2 });
+        assert_eq!(migrate(current.clone()).unwrap(), current);
+        assert!(migrate(json!({ "schema_version": SCHEMA_VERSION + 1 })).is_err());
+        assert!(migrate(json!([])).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::events::DownloadEvent;
+use crate::persistence::save_state;
+use crate::{now_secs, AppState, DownloadStatus};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Something to do with a file once its download has completed.
+///
+/// `RunCommand` expands `{path}`, `{dir}`, `{name}` and `{url}` (each quoted
+/// for the platform shell) before running it through `sh -c` / `cmd /C`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PostAction {
+    RunCommand { command: String },
+    MoveTo { dir: PathBuf },
+    Open,
+    DeleteAfter { days: u32 },
+}
+
+/// Outcome of one post-download action, kept on the `Download`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActionResult {
+    pub action: String,
+    pub ok: bool,
+    pub message: String,
+    pub at: u64,
+}
+
+impl PostAction {
+    fn label(&self) -> String {
+        match self {
+            PostAction::RunCommand { .. } => "run_command".to_string(),
+            PostAction::MoveTo { .. } => "move_to".to_string(),
+            PostAction::Open => "open".to_string(),
+            PostAction::DeleteAfter { .. } => "delete_after".to_string(),
+        }
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+fn expand_placeholders(template: &str, path: &Path, url: &str) -> String {
+    let dir = path.parent().unwrap_or(Path::new("")).to_string_lossy();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    template
+        .replace("{path}", &shell_quote(&path.to_string_lossy()))
+        .replace("{dir}", &shell_quote(&dir))
+        .replace("{name}", &shell_quote(&name))
+        .replace("{url}", &shell_quote(url))
+}
+
+fn run_command(command: &str) -> Result<String, String> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .map_err(|e| e.to_string())?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(format!("{}: {}", output.status, stderr))
+    }
+}
+
+fn move_file(from: &Path, dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let to = dir.join(from.file_name().unwrap_or_default());
+    if std::fs::rename(from, &to).is_err() {
+        // Different volume: fall back to copy and remove.
+        std::fs::copy(from, &to).map_err(|e| e.to_string())?;
+        std::fs::remove_file(from).map_err(|e| e.to_string())?;
+    }
+    Ok(to)
+}
+
+#[cfg(desktop)]
+pub fn open_file(path: &Path) -> Result<(), String> {
+    tauri_plugin_opener::open_path(path, None::<&str>).map_err(|e| e.to_string())
+}
+
+#[cfg(mobile)]
+pub fn open_file(_path: &Path) -> Result<(), String> {
+    Err("Opening files is not supported on this platform".to_string())
+}
+
+/// Runs the download's own actions, or the global ones if it has none, in
+/// order. A `MoveTo` updates `file_path` so later actions see the new path.
+pub async fn run_post_actions(app_handle: AppHandle, id: u64) {
+    let state = app_handle.state::<AppState>().inner().clone();
+    let Some(download) = state.downloads.lock().await.iter().find(|d| d.id == id).cloned() else {
+        return;
+    };
+    let actions = match &download.post_actions {
+        Some(actions) => actions.clone(),
+        None => state.post_actions.lock().await.clone(),
+    };
+    if actions.is_empty() {
+        return;
+    }
+
+    let mut path = download.file_path.clone();
+    let mut results = Vec::new();
+    let mut delete_after = None;
+
+    for action in &actions {
+        let outcome = match action {
+            PostAction::RunCommand { command } => {
+                let command = expand_placeholders(command, &path, &download.url);
+                tokio::task::spawn_blocking(move || run_command(&command))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|r| r)
+            }
+            PostAction::MoveTo { dir } => {
+                let (from, dir) = (path.clone(), dir.clone());
+                match tokio::task::spawn_blocking(move || move_file(&from, &dir))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|r| r)
+                {
+                    Ok(to) => {
+                        path = to;
+                        Ok(format!("Moved to {}", path.display()))
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            PostAction::Open => open_file(&path).map(|_| "Opened".to_string()),
+            PostAction::DeleteAfter { days } => {
+                let at = now_secs() + u64::from(*days) * 24 * 60 * 60;
+                delete_after = Some(at);
+                Ok(format!("Scheduled for deletion in {} days", days))
+            }
+        };
+
+        let (ok, message) = match outcome {
+            Ok(message) => (true, message),
+            Err(message) => (false, message),
+        };
+        results.push(ActionResult {
+            action: action.label(),
+            ok,
+            message,
+            at: now_secs(),
+        });
+    }
+
+    let mut downloads = state.downloads.lock().await;
+    if let Some(dl) = downloads.iter_mut().find(|d| d.id == id) {
+        dl.file_path = path;
+        dl.action_log.extend(results);
+        if delete_after.is_some() {
+            dl.delete_after = delete_after;
+        }
+        state.events.emit(DownloadEvent::Updated { download: dl.clone() });
+    }
+    drop(downloads);
+    save_state(&app_handle, &state).await;
+}
+
+/// Deletes completed files whose `DeleteAfter` time has passed.
+pub fn spawn_expiry_sweeper(app_handle: AppHandle) {
+    tokio::spawn(async move {
+        let state = app_handle.state::<AppState>().inner().clone();
+        loop {
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+            let now = now_secs();
+            let mut changed = false;
+            let mut downloads = state.downloads.lock().await;
+            for dl in downloads.iter_mut() {
+                if dl.status != DownloadStatus::Completed || dl.delete_after.is_none_or(|at| at > now) {
+                    continue;
+                }
+                let (ok, message) = match std::fs::remove_file(&dl.file_path) {
+                    Ok(()) => (true, format!("Deleted {}", dl.file_path.display())),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        (true, "File was already gone".to_string())
+                    }
+                    Err(e) => (false, e.to_string()),
+                };
+                dl.delete_after = None;
+                dl.action_log.push(ActionResult {
+                    action: "delete_after".to_string(),
+                    ok,
+                    message,
+                    at: now,
+                });
+                state.events.emit(DownloadEvent::Updated { download: dl.clone() });
+                changed = true;
+            }
+            drop(downloads);
+            if changed {
+                save_state(&app_handle, &state).await;
+            }
+        }
+    });
+}
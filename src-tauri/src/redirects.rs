@@ -0,0 +1,64 @@
+use std::sync::{Arc, Mutex};
+
+use reqwest::redirect::Policy;
+use reqwest::ClientBuilder;
+use serde::{Deserialize, Serialize};
+
+/// How redirects are followed and whether resumes reuse the resolved URL.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RedirectSettings {
+    pub max_redirects: usize,
+    /// Follow redirects from `https` to plain `http`.
+    pub allow_https_downgrade: bool,
+    /// On resume, try `Download::effective_url` first and only re-resolve
+    /// from the original URL if it no longer works (e.g. an expired CDN
+    /// link). Off means always start from the original URL.
+    pub reuse_effective_url: bool,
+}
+
+impl Default for RedirectSettings {
+    fn default() -> Self {
+        RedirectSettings {
+            max_redirects: 10,
+            allow_https_downgrade: false,
+            reuse_effective_url: true,
+        }
+    }
+}
+
+/// The URLs visited by the most recent request made with the client, from
+/// the requested URL to the last redirect target. Empty if it didn't
+/// redirect.
+#[derive(Clone, Default)]
+pub struct RedirectLog(Arc<Mutex<Vec<String>>>);
+
+impl RedirectLog {
+    pub fn take(&self) -> Vec<String> {
+        self.0.lock().map(|mut chain| std::mem::take(&mut *chain)).unwrap_or_default()
+    }
+}
+
+impl RedirectSettings {
+    pub fn apply(&self, builder: ClientBuilder, log: &RedirectLog) -> ClientBuilder {
+        let settings = self.clone();
+        let log = log.clone();
+        builder.redirect(Policy::custom(move |attempt| {
+            let mut chain: Vec<String> = attempt.previous().iter().map(|u| u.to_string()).collect();
+            chain.push(attempt.url().to_string());
+            if let Ok(mut recorded) = log.0.lock() {
+                *recorded = chain;
+            }
+
+            let downgrade = attempt.url().scheme() == "http"
+                && attempt.previous().last().is_some_and(|u| u.scheme() == "https");
+            if attempt.previous().len() > settings.max_redirects {
+                attempt.error(format!("More than {} redirects", settings.max_redirects))
+            } else if downgrade && !settings.allow_https_downgrade {
+                attempt.error("Refusing to follow a redirect from HTTPS to HTTP")
+            } else {
+                attempt.follow()
+            }
+        }))
+    }
+}
@@ -0,0 +1,478 @@
+use std::fmt;
+
+use futures_util::future::BoxFuture;
+use futures_util::{FutureExt, StreamExt};
+use reqwest::header::{HeaderMap, ACCEPT, CONTENT_TYPE, USER_AGENT};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::headers::RequestHeaders;
+
+/// Pages larger than this are not scanned for links.
+const MAX_PAGE_BYTES: usize = 2 * 1024 * 1024;
+
+/// A direct download found behind a page URL.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ResolvedLink {
+    pub url: String,
+    /// Name the site gives the file, used instead of the one in the URL.
+    pub file_name: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// Turns a landing page (a release page, a file host, ...) into the files
+/// behind it. Resolvers run in registry order; the first one that matches a
+/// URL and finds links wins. An empty result means "not mine after all" and
+/// lets the next resolver try.
+pub trait UrlResolver: Send + Sync {
+    /// Stable name, used to switch the resolver off in `ResolverSettings`.
+    fn name(&self) -> &'static str;
+
+    /// Cheap check on the URL alone, before any request is made.
+    fn matches(&self, url: &Url) -> bool;
+
+    /// Off until listed in `ResolverSettings::opted_in`. For resolvers that
+    /// make requests for URLs that aren't obviously theirs.
+    fn opt_in(&self) -> bool {
+        false
+    }
+
+    fn resolve<'a>(
+        &'a self,
+        client: &'a Client,
+        url: &'a Url,
+        headers: &'a RequestHeaders,
+    ) -> BoxFuture<'a, Result<Vec<ResolvedLink>, String>>;
+}
+
+/// Which resolvers run before a link is enqueued.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ResolverSettings {
+    pub enabled: bool,
+    /// Names of resolvers to skip, see `UrlResolver::name`.
+    pub disabled: Vec<String>,
+    /// Names of opt-in resolvers to run, see `UrlResolver::opt_in`.
+    pub opted_in: Vec<String>,
+}
+
+impl Default for ResolverSettings {
+    fn default() -> Self {
+        ResolverSettings {
+            enabled: true,
+            disabled: Vec::new(),
+            opted_in: Vec::new(),
+        }
+    }
+}
+
+impl ResolverSettings {
+    fn allows(&self, resolver: &dyn UrlResolver) -> bool {
+        let name = resolver.name();
+        if resolver.opt_in() {
+            self.opted_in.iter().any(|n| n == name)
+        } else {
+            !self.disabled.iter().any(|n| n == name)
+        }
+    }
+}
+
+/// A registered resolver, as listed in the settings.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolverInfo {
+    pub name: &'static str,
+    pub opt_in: bool,
+}
+
+/// What a resolver made of a URL.
+#[derive(Debug, Clone, Serialize)]
+pub struct Resolution {
+    pub resolver: &'static str,
+    pub links: Vec<ResolvedLink>,
+}
+
+pub struct ResolverRegistry {
+    resolvers: Vec<Box<dyn UrlResolver>>,
+}
+
+impl Default for ResolverRegistry {
+    /// The built-in resolvers, most specific first.
+    fn default() -> Self {
+        let mut registry = ResolverRegistry {
+            resolvers: Vec::new(),
+        };
+        registry.register(GitHubReleases);
+        registry.register(AnchorDownloads);
+        registry
+    }
+}
+
+impl fmt::Debug for ResolverRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+impl ResolverRegistry {
+    /// Adds a resolver after the existing ones. A generic resolver that
+    /// matches every URL should be registered last.
+    pub fn register(&mut self, resolver: impl UrlResolver + 'static) {
+        self.resolvers.push(Box::new(resolver));
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.resolvers.iter().map(|r| r.name()).collect()
+    }
+
+    pub fn list(&self) -> Vec<ResolverInfo> {
+        self.resolvers
+            .iter()
+            .map(|r| ResolverInfo {
+                name: r.name(),
+                opt_in: r.opt_in(),
+            })
+            .collect()
+    }
+
+    /// Runs the enabled resolvers that match `url` until one finds links.
+    /// `None` means the URL should be downloaded as it is. Resolver errors
+    /// are logged and skipped so a broken site can't block enqueueing.
+    pub async fn resolve(
+        &self,
+        client: &Client,
+        url: &str,
+        headers: &RequestHeaders,
+        settings: &ResolverSettings,
+    ) -> Option<Resolution> {
+        if !settings.enabled {
+            return None;
+        }
+        let parsed = Url::parse(url).ok()?;
+        for resolver in &self.resolvers {
+            if !settings.allows(resolver.as_ref()) || !resolver.matches(&parsed) {
+                continue;
+            }
+            match resolver.resolve(client, &parsed, headers).await {
+                Ok(links) if !links.is_empty() => {
+                    return Some(Resolution {
+                        resolver: resolver.name(),
+                        links,
+                    })
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Resolver {} failed for {}: {}", resolver.name(), url, e),
+            }
+        }
+        None
+    }
+}
+
+/// Release pages on github.com (`/<owner>/<repo>/releases`, `.../latest`
+/// and `.../tag/<tag>`), resolved to their assets through the GitHub API.
+pub struct GitHubReleases;
+
+impl GitHubReleases {
+    /// The API endpoint for a release page, if `url` is one.
+    fn api_url(url: &Url) -> Option<String> {
+        if url.host_str() != Some("github.com") {
+            return None;
+        }
+        let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+        match segments.as_slice() {
+            [owner, repo, "releases"] | [owner, repo, "releases", "latest"] => Some(format!(
+                "https://api.github.com/repos/{}/{}/releases/latest",
+                owner, repo
+            )),
+            [owner, repo, "releases", "tag", tag] => Some(format!(
+                "https://api.github.com/repos/{}/{}/releases/tags/{}",
+                owner, repo, tag
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GitHubRelease {
+    assets: Vec<GitHubAsset>,
+}
+
+#[derive(Deserialize)]
+struct GitHubAsset {
+    name: String,
+    size: u64,
+    browser_download_url: String,
+}
+
+impl UrlResolver for GitHubReleases {
+    fn name(&self) -> &'static str {
+        "github_releases"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        Self::api_url(url).is_some()
+    }
+
+    fn resolve<'a>(
+        &'a self,
+        client: &'a Client,
+        url: &'a Url,
+        _headers: &'a RequestHeaders,
+    ) -> BoxFuture<'a, Result<Vec<ResolvedLink>, String>> {
+        async move {
+            let api_url = Self::api_url(url).ok_or("Not a release page")?;
+            let release: GitHubRelease = client
+                .get(&api_url)
+                .header(ACCEPT, "application/vnd.github+json")
+                // The API rejects requests without a user agent.
+                .header(USER_AGENT, "MadByte")
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status())
+                .map_err(|e| e.to_string())?
+                .json()
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(release
+                .assets
+                .into_iter()
+                .map(|asset| ResolvedLink {
+                    url: asset.browser_download_url,
+                    file_name: Some(asset.name),
+                    size: Some(asset.size),
+                })
+                .collect())
+        }
+        .boxed()
+    }
+}
+
+/// Any HTML page: collects the `<a download href=...>` links on it. URLs
+/// that don't serve HTML are left alone, only their headers are read. Opt-in,
+/// since it requests every URL before it is downloaded and swaps a page for
+/// the links on it.
+pub struct AnchorDownloads;
+
+/// `name` and value of each attribute in the inside of a tag, e.g.
+/// `a href="x" download`. Names are lowercased, values unquoted.
+fn attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = tag.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].trim_end_matches('/').to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (val, remaining) = match after_eq.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &after_eq[1..];
+                    let end = inner.find(quote).unwrap_or(inner.len());
+                    (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                    (&after_eq[..end], &after_eq[end..])
+                }
+            };
+            value = val.to_string();
+            rest = remaining.trim_start();
+        }
+        if !name.is_empty() {
+            attrs.push((name, value));
+        }
+    }
+    attrs
+}
+
+pub(crate) fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// The attributes of every `<a ...>` tag in `html`, in page order.
+pub(crate) fn anchor_tags(html: &str) -> Vec<Vec<(String, String)>> {
+    let lower = html.to_ascii_lowercase();
+    let mut tags = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = lower[pos..].find("<a") {
+        let start = pos + found + 2;
+        let Some(len) = lower[start..].find('>') else {
+            break;
+        };
+        pos = start + len;
+        if lower[start..].starts_with(|c: char| c.is_whitespace()) {
+            tags.push(attributes(&html[start..pos]));
+        }
+    }
+    tags
+}
+
+/// The `<a download>` links in `html`, resolved against `base`.
+fn download_anchors(html: &str, base: &Url) -> Vec<ResolvedLink> {
+    let mut links: Vec<ResolvedLink> = Vec::new();
+    for attrs in anchor_tags(html) {
+        let Some((_, download)) = attrs.iter().find(|(name, _)| name == "download") else {
+            continue;
+        };
+        let Some((_, href)) = attrs.iter().find(|(name, _)| name == "href") else {
+            continue;
+        };
+        let Ok(url) = base.join(&decode_entities(href)) else {
+            continue;
+        };
+        if !matches!(url.scheme(), "http" | "https") || links.iter().any(|l| l.url == url.as_str()) {
+            continue;
+        }
+        // The suggested name may not point into another folder.
+        let file_name = std::path::Path::new(&decode_entities(download))
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned());
+        links.push(ResolvedLink {
+            url: url.to_string(),
+            file_name,
+            size: None,
+        });
+    }
+    links
+}
+
+fn is_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.to_ascii_lowercase().starts_with("text/html"))
+}
+
+/// Fetches `url` and returns its final URL and body if it is an HTML page of
+/// at most `MAX_PAGE_BYTES`. Anything else is `None`. `origin` is the link
+/// `headers` were captured with, see `RequestHeaders::apply`.
+pub(crate) async fn fetch_html(
+    client: &Client,
+    url: &Url,
+    headers: &RequestHeaders,
+    origin: &Url,
+) -> Result<Option<(Url, String)>, String> {
+    let resp = headers
+        .apply(client.get(url.as_str()), url.as_str(), origin.as_str())
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() || !is_html(resp.headers()) {
+        return Ok(None);
+    }
+    let base = resp.url().clone();
+
+    let mut body = Vec::new();
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.map_err(|e| e.to_string())?);
+        if body.len() > MAX_PAGE_BYTES {
+            return Ok(None);
+        }
+    }
+    Ok(Some((base, String::from_utf8_lossy(&body).into_owned())))
+}
+
+impl UrlResolver for AnchorDownloads {
+    fn name(&self) -> &'static str {
+        "anchor_download"
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        matches!(url.scheme(), "http" | "https")
+    }
+
+    fn opt_in(&self) -> bool {
+        true
+    }
+
+    fn resolve<'a>(
+        &'a self,
+        client: &'a Client,
+        url: &'a Url,
+        headers: &'a RequestHeaders,
+    ) -> BoxFuture<'a, Result<Vec<ResolvedLink>, String>> {
+        async move {
+            // Only the headers of a file, so the download that follows is the
+            // one request that uses up a one-time link. Servers that refuse
+            // HEAD get the GET below.
+            if let Ok(head) = headers.apply(client.head(url.as_str()), url.as_str(), url.as_str()).send().await {
+                if head.status().is_success() && !is_html(head.headers()) {
+                    return Ok(Vec::new());
+                }
+            }
+            // Not HTML: a file, or an error the download itself will report.
+            Ok(match fetch_html(client, url, headers, url).await? {
+                Some((base, html)) => download_anchors(&html, &base),
+                None => Vec::new(),
+            })
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn anchor_tags_reads_attributes() {
+        let html = r#"<p>Hi</p><A HREF="/a.zip" Download>one</A>
+            <abbr title="x">no</abbr><a class=btn href='b.zip' download="B &amp; C.zip"/>"#;
+        assert_eq!(
+            anchor_tags(html),
+            vec![
+                vec![pair("href", "/a.zip"), pair("download", "")],
+                vec![pair("class", "btn"), pair("href", "b.zip"), pair("download", "B &amp; C.zip")],
+            ]
+        );
+        assert!(anchor_tags("<a href=\"never closed").is_empty());
+    }
+
+    #[test]
+    fn download_anchors_resolves_and_dedupes() {
+        let base = Url::parse("https://example.com/files/").unwrap();
+        let html = r#"<a href="a.zip" download>a</a>
+            <a href="/plain.html">no download attribute</a>
+            <a href="a.zip" download>again</a>
+            <a href="javascript:void(0)" download>script</a>
+            <a href="b.bin" download="../../b &amp; c.bin">b</a>"#;
+        let found: Vec<(String, Option<String>)> = download_anchors(html, &base)
+            .into_iter()
+            .map(|l| (l.url, l.file_name))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("https://example.com/files/a.zip".to_string(), None),
+                ("https://example.com/files/b.bin".to_string(), Some("b & c.bin".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn settings_allow_opt_in_resolvers_only_when_listed() {
+        let mut settings = ResolverSettings::default();
+        assert!(settings.allows(&GitHubReleases));
+        assert!(!settings.allows(&AnchorDownloads));
+
+        settings.disabled.push(GitHubReleases.name().to_string());
+        settings.opted_in.push(AnchorDownloads.name().to_string());
+        assert!(!settings.allows(&GitHubReleases));
+        assert!(settings.allows(&AnchorDownloads));
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime};
+
+use chrono::DateTime;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+
+/// Longest `Retry-After` we are willing to wait, so a misbehaving server
+/// can't park a download for days.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// How failed attempts are retried. Set globally, or per download through
+/// `Download::retry_policy`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Fraction of the delay (0.0-1.0) randomly added or removed.
+    pub jitter: f64,
+    /// HTTP statuses worth retrying. Connection and stream errors always are.
+    pub retry_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 10,
+            base_delay_ms: 2_000,
+            max_delay_ms: 60_000,
+            jitter: 0.2,
+            retry_statuses: vec![408, 425, 429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn is_retryable_status(&self, status: u16) -> bool {
+        self.retry_statuses.contains(&status)
+    }
+
+    /// Delay before retry number `attempt` (1-based): exponential backoff
+    /// from `base_delay_ms` capped at `max_delay_ms`, with jitter. A server
+    /// supplied `Retry-After` takes precedence.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(wait) = retry_after {
+            return wait.min(MAX_RETRY_AFTER);
+        }
+        let exp = attempt.saturating_sub(1).min(31);
+        let backoff = self
+            .base_delay_ms
+            .saturating_mul(1u64 << exp)
+            .min(self.max_delay_ms.max(self.base_delay_ms));
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 + jitter * (2.0 * random_unit() - 1.0);
+        Duration::from_millis((backoff as f64 * factor) as u64)
+    }
+}
+
+/// A uniformly distributed value in `[0, 1)`, good enough for jitter.
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Reads `Retry-After` as either delay-seconds or an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = at.timestamp() - chrono::Utc::now().timestamp();
+    Some(Duration::from_secs(wait.max(0) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn no_jitter() -> RetryPolicy {
+        RetryPolicy {
+            base_delay_ms: 1_000,
+            max_delay_ms: 10_000,
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn delay_doubles_up_to_the_cap() {
+        let policy = no_jitter();
+        assert_eq!(policy.delay(1, None), Duration::from_secs(1));
+        assert_eq!(policy.delay(2, None), Duration::from_secs(2));
+        assert_eq!(policy.delay(4, None), Duration::from_secs(8));
+        assert_eq!(policy.delay(5, None), Duration::from_secs(10));
+        assert_eq!(policy.delay(200, None), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn delay_jitter_stays_in_range() {
+        let policy = RetryPolicy { jitter: 0.5, ..no_jitter() };
+        for _ in 0..100 {
+            let delay = policy.delay(1, None);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1_500));
+        }
+    }
+
+    #[test]
+    fn delay_prefers_retry_after_within_limit() {
+        let policy = no_jitter();
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(30))), Duration::from_secs(30));
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(86_400))), MAX_RETRY_AFTER);
+    }
+
+    #[test]
+    fn retry_after_reads_seconds_and_dates() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static(" 120 "));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        let soon = (chrono::Utc::now() + chrono::Duration::seconds(600)).to_rfc2822();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(&soon).unwrap());
+        let wait = retry_after(&headers).unwrap();
+        assert!(wait > Duration::from_secs(590) && wait <= Duration::from_secs(600));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+}
@@ -0,0 +1,344 @@
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use rhai::{CallFnOptions, Dynamic, Engine, Scope, AST};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::events::DownloadEvent;
+use crate::post_actions::PostAction;
+use crate::{AppState, Download};
+
+const SCRIPTS_DIR: &str = "scripts";
+const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Where in a download's life a script function is called. Each script may
+/// define any of these functions; `this` is the `ScriptDownload`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptHook {
+    /// `on_enqueue()`: may rewrite `url`, `mirrors`, `file_name`, `dir` and
+    /// `category`, or set `reject`.
+    Enqueue,
+    /// `before_start()`, before every attempt: may rewrite `url`, `mirrors`
+    /// and `category`, or set `reject` to fail the download.
+    BeforeStart,
+    /// `on_complete()`: may change `category` and set `post_actions`, see
+    /// `script_may_set`.
+    Complete,
+    /// `on_fail()`: may change `category` and set `post_actions`.
+    Fail,
+}
+
+impl ScriptHook {
+    pub fn function(self) -> &'static str {
+        match self {
+            ScriptHook::Enqueue => "on_enqueue",
+            ScriptHook::BeforeStart => "before_start",
+            ScriptHook::Complete => "on_complete",
+            ScriptHook::Fail => "on_fail",
+        }
+    }
+
+    const ALL: [ScriptHook; 4] = [
+        ScriptHook::Enqueue,
+        ScriptHook::BeforeStart,
+        ScriptHook::Complete,
+        ScriptHook::Fail,
+    ];
+}
+
+/// The view of a download a script works on, as a map bound to `this`.
+/// Only the fields listed on each `ScriptHook` are read back.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScriptDownload {
+    /// 0 at enqueue time, before the download exists.
+    pub id: u64,
+    pub url: String,
+    pub mirrors: Vec<String>,
+    pub file_name: String,
+    pub dir: String,
+    pub category: String,
+    pub status: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub error: Option<String>,
+    pub referer: Option<String>,
+    /// Set to a reason to refuse the download.
+    pub reject: Option<String>,
+    /// Set to replace the download's post-download actions.
+    pub post_actions: Option<Vec<PostAction>>,
+}
+
+impl ScriptDownload {
+    pub fn of(download: &Download) -> Self {
+        ScriptDownload {
+            id: download.id,
+            url: download.url.clone(),
+            mirrors: download.mirrors.clone(),
+            file_name: download
+                .file_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            dir: download
+                .file_path
+                .parent()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            category: download.category.clone(),
+            status: download.status.name().to_string(),
+            downloaded_bytes: download.downloaded_bytes,
+            total_bytes: download.total_bytes,
+            error: download.error.as_ref().map(|e| e.message.clone()),
+            referer: download.headers.referer.clone(),
+            reject: None,
+            post_actions: None,
+        }
+    }
+}
+
+/// Whether user scripts run at all. Off by default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ScriptSettings {
+    pub enabled: bool,
+}
+
+/// A loaded script, or why it failed to load.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptStatus {
+    pub name: String,
+    /// Hook functions the script defines.
+    pub hooks: Vec<&'static str>,
+    pub error: Option<String>,
+}
+
+struct Script {
+    name: String,
+    ast: AST,
+}
+
+/// The Rhai engine and the scripts loaded from the `scripts` folder in the
+/// app data directory. Scripts have no file, process or network access of
+/// their own and are cut off after a fixed amount of work; the actions they
+/// may leave on a download are limited by `script_may_set`.
+pub struct ScriptHost {
+    engine: Engine,
+    scripts: RwLock<Vec<Script>>,
+    statuses: RwLock<Vec<ScriptStatus>>,
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(1_000_000)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(64 * 1024)
+            .set_max_array_size(10_000)
+            .set_max_map_size(10_000)
+            .set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new())
+            .on_print(|text| eprintln!("[script] {}", text))
+            .on_debug(|text, source, pos| {
+                eprintln!("[script {}:{}] {}", source.unwrap_or(""), pos, text)
+            });
+        engine.disable_symbol("eval");
+        ScriptHost {
+            engine,
+            scripts: RwLock::new(Vec::new()),
+            statuses: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl fmt::Debug for ScriptHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptHost")
+            .field("scripts", &self.statuses())
+            .finish()
+    }
+}
+
+impl ScriptHost {
+    /// Replaces the loaded scripts with the `*.rhai` files in `dir`, in
+    /// file name order. Scripts that fail to compile are reported and skipped.
+    pub fn load_dir(&self, dir: &Path) -> Vec<ScriptStatus> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.extension().is_some_and(|ext| ext == SCRIPT_EXTENSION))
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+
+        let mut scripts = Vec::new();
+        let mut statuses = Vec::new();
+        for path in paths {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match self.engine.compile_file(path) {
+                Ok(ast) => {
+                    let hooks = ScriptHook::ALL
+                        .iter()
+                        .map(|h| h.function())
+                        .filter(|f| ast.iter_functions().any(|meta| meta.name == *f))
+                        .collect();
+                    statuses.push(ScriptStatus {
+                        name: name.clone(),
+                        hooks,
+                        error: None,
+                    });
+                    scripts.push(Script { name, ast });
+                }
+                Err(e) => statuses.push(ScriptStatus {
+                    name,
+                    hooks: Vec::new(),
+                    error: Some(e.to_string()),
+                }),
+            }
+        }
+
+        if let Ok(mut loaded) = self.scripts.write() {
+            *loaded = scripts;
+        }
+        if let Ok(mut current) = self.statuses.write() {
+            *current = statuses.clone();
+        }
+        statuses
+    }
+
+    pub fn statuses(&self) -> Vec<ScriptStatus> {
+        self.statuses.read().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Calls `hook` in every script that defines it, feeding each one the
+    /// result of the previous. Stops at the first `reject`. `None` when no
+    /// script has the hook.
+    pub fn run(&self, hook: ScriptHook, download: ScriptDownload) -> Option<ScriptDownload> {
+        let scripts = self.scripts.read().ok()?;
+        let function = hook.function();
+        let mut current = download;
+        let mut ran = false;
+
+        for script in scripts.iter() {
+            if !script.ast.iter_functions().any(|meta| meta.name == function) {
+                continue;
+            }
+            let mut this = match rhai::serde::to_dynamic(&current) {
+                Ok(value) => value,
+                Err(e) => {
+                    eprintln!("Script {}: {}", script.name, e);
+                    continue;
+                }
+            };
+            let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut this);
+            let result =
+                self.engine
+                    .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &script.ast, function, ());
+            if let Err(e) = result {
+                eprintln!("Script {} failed in {}: {}", script.name, function, e);
+                continue;
+            }
+            match rhai::serde::from_dynamic::<ScriptDownload>(&this) {
+                Ok(changed) => current = changed,
+                Err(e) => {
+                    eprintln!("Script {} left `this` unusable in {}: {}", script.name, function, e);
+                    continue;
+                }
+            }
+            ran = true;
+            if current.reject.is_some() {
+                break;
+            }
+        }
+        ran.then_some(current)
+    }
+}
+
+pub fn scripts_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(SCRIPTS_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// (Re)loads the scripts folder into the app's `ScriptHost`.
+pub fn load_scripts(app_handle: &AppHandle) -> Result<Vec<ScriptStatus>, String> {
+    let dir = scripts_dir(app_handle)?;
+    Ok(app_handle.state::<AppState>().scripts.load_dir(&dir))
+}
+
+/// Runs `hook` off the async runtime if scripting is enabled.
+pub async fn run_hook(
+    state: &AppState,
+    hook: ScriptHook,
+    download: ScriptDownload,
+) -> Option<ScriptDownload> {
+    if !state.script_settings.lock().await.enabled {
+        return None;
+    }
+    let host: Arc<ScriptHost> = state.scripts.clone();
+    tokio::task::spawn_blocking(move || host.run(hook, download))
+        .await
+        .ok()
+        .flatten()
+}
+
+fn is_below(path: &Path, dir: &Path) -> bool {
+    path.strip_prefix(dir)
+        .is_ok_and(|rest| rest.components().all(|c| matches!(c, Component::Normal(_))))
+}
+
+/// Whether a script may leave `action` on a download saved at `file_path`.
+/// Commands are for the user to set up; files are only moved or deleted
+/// below the download folder.
+fn script_may_set(action: &PostAction, file_path: &Path, download_dir: &Path) -> bool {
+    match action {
+        PostAction::RunCommand { .. } => false,
+        PostAction::MoveTo { dir } => is_below(file_path, download_dir) && is_below(dir, download_dir),
+        PostAction::DeleteAfter { .. } => is_below(file_path, download_dir),
+        PostAction::Open => true,
+    }
+}
+
+/// Runs `on_complete` or `on_fail` for `id` and applies the changes. Returns
+/// whether the script set `post_actions`. Actions it may not set are dropped.
+pub async fn run_finish_hook(app_handle: &AppHandle, id: u64, hook: ScriptHook) -> bool {
+    let state = app_handle.state::<AppState>().inner().clone();
+    let Some(download) = state.downloads.lock().await.iter().find(|d| d.id == id).cloned() else {
+        return false;
+    };
+    let Some(changed) = run_hook(&state, hook, ScriptDownload::of(&download)).await else {
+        return false;
+    };
+
+    let download_dir = state.download_dir.lock().await.clone();
+    let mut downloads = state.downloads.lock().await;
+    let Some(dl) = downloads.iter_mut().find(|d| d.id == id) else {
+        return false;
+    };
+    dl.category = changed.category;
+    let has_actions = changed.post_actions.is_some();
+    if let Some(mut actions) = changed.post_actions {
+        actions.retain(|action| {
+            let allowed = script_may_set(action, &dl.file_path, &download_dir);
+            if !allowed {
+                eprintln!("Script action {:?} on download {} ignored", action, id);
+            }
+            allowed
+        });
+        dl.post_actions = Some(actions);
+    }
+    state.events.emit(DownloadEvent::Updated { download: dl.clone() });
+    has_actions
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{DateTime, Datelike};
+use serde::Serialize;
+use url::Url;
+
+use crate::{Download, DownloadStatus};
+
+/// Totals for one host or category.
+#[derive(Debug, Default, Serialize)]
+pub struct GroupStats {
+    pub downloads: u64,
+    pub completed: u64,
+    pub failed: u64,
+    pub bytes: u64,
+    pub average_speed_kbps: f64,
+    pub failure_rate: f64,
+    #[serde(skip)]
+    active_secs: u64,
+    #[serde(skip)]
+    completed_bytes: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DownloadStats {
+    #[serde(flatten)]
+    pub totals: GroupStats,
+    /// Keyed by `YYYY-MM-DD` (UTC).
+    pub bytes_per_day: BTreeMap<String, u64>,
+    /// Keyed by ISO week, `YYYY-Www`.
+    pub bytes_per_week: BTreeMap<String, u64>,
+    pub hosts: BTreeMap<String, GroupStats>,
+    pub categories: BTreeMap<String, GroupStats>,
+}
+
+impl GroupStats {
+    fn add(&mut self, dl: &Download) {
+        self.downloads += 1;
+        self.bytes += dl.downloaded_bytes;
+        match dl.status {
+            DownloadStatus::Completed => {
+                self.completed += 1;
+                self.completed_bytes += dl.downloaded_bytes;
+                self.active_secs += dl.active_secs;
+            }
+            DownloadStatus::Failed(_) => self.failed += 1,
+            _ => {}
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.active_secs > 0 {
+            self.average_speed_kbps = self.completed_bytes as f64 / 1024.0 / self.active_secs as f64;
+        }
+        let finished = self.completed + self.failed;
+        if finished > 0 {
+            self.failure_rate = self.failed as f64 / finished as f64;
+        }
+    }
+}
+
+/// Groups a file into a broad category by extension, used when the download
+/// has no category of its own.
+pub fn category_for_path(path: &Path) -> String {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let category = match ext.as_str() {
+        "zip" | "rar" | "7z" | "gz" | "xz" | "bz2" | "zst" | "tar" | "tgz" => "archives",
+        "mp4" | "mkv" | "avi" | "mov" | "webm" | "flv" | "wmv" => "video",
+        "mp3" | "flac" | "wav" | "ogg" | "m4a" | "aac" | "opus" => "audio",
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "bmp" => "images",
+        "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "txt" | "epub" => "documents",
+        "exe" | "msi" | "dmg" | "pkg" | "deb" | "rpm" | "appimage" | "apk" => "programs",
+        "iso" | "img" => "disk images",
+        _ => "other",
+    };
+    category.to_string()
+}
+
+fn host_of(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Aggregates `downloads`, optionally only those created at or after
+/// `since` (unix seconds). Bytes are attributed to the day the download
+/// completed, or started if it hasn't finished yet.
+pub fn compute(downloads: &[Download], since: Option<u64>) -> DownloadStats {
+    let mut stats = DownloadStats::default();
+
+    for dl in downloads {
+        if let Some(since) = since {
+            if dl.created_at.unwrap_or(0) < since {
+                continue;
+            }
+        }
+
+        stats.totals.add(dl);
+        stats.hosts.entry(host_of(&dl.url)).or_default().add(dl);
+        let category = if dl.category.is_empty() {
+            category_for_path(&dl.file_path)
+        } else {
+            dl.category.clone()
+        };
+        stats.categories.entry(category).or_default().add(dl);
+
+        let when = dl.completed_at.or(dl.started_at).or(dl.created_at);
+        if let Some(date) = when.and_then(|secs| DateTime::from_timestamp(secs as i64, 0)) {
+            *stats
+                .bytes_per_day
+                .entry(date.format("%Y-%m-%d").to_string())
+                .or_default() += dl.downloaded_bytes;
+            let week = date.iso_week();
+            *stats
+                .bytes_per_week
+                .entry(format!("{}-W{:02}", week.year(), week.week()))
+                .or_default() += dl.downloaded_bytes;
+        }
+    }
+
+    stats.totals.finish();
+    stats.hosts.values_mut().for_each(GroupStats::finish);
+    stats.categories.values_mut().for_each(GroupStats::finish);
+    stats
+}
@@ -0,0 +1,400 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{Map, Value};
+
+/// Bump together with a new step in `Store::create_schema`.
+const DB_VERSION: i32 = 1;
+/// How often a save compares every download with the database anyway, to
+/// catch changes no event reported.
+const FULL_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// SQLite-backed storage for downloads, queue order and settings.
+///
+/// Downloads are reported as changed through `mark_changed`, and a save only
+/// serializes and writes those, so thousands of history entries don't make
+/// saving every few seconds expensive. Rows whose content didn't actually
+/// change are skipped as well.
+pub struct Store {
+    conn: Mutex<Connection>,
+    written: Mutex<Written>,
+    changes: Mutex<Changes>,
+}
+
+/// What the database currently holds, as serialized rows.
+#[derive(Default)]
+struct Written {
+    downloads: HashMap<u64, String>,
+    queue: Vec<u64>,
+    settings: HashMap<String, String>,
+}
+
+/// Downloads changed since the last save.
+struct Changes {
+    ids: HashSet<u64>,
+    /// Everything has to be compared, e.g. after events were missed.
+    all: bool,
+    last_full: Instant,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        Self::from_connection(conn)
+    }
+
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> rusqlite::Result<Self> {
+        Self::create_schema(&conn)?;
+        Ok(Store {
+            conn: Mutex::new(conn),
+            written: Mutex::new(Written::default()),
+            changes: Mutex::new(Changes {
+                ids: HashSet::new(),
+                all: false,
+                last_full: Instant::now(),
+            }),
+        })
+    }
+
+    fn create_schema(conn: &Connection) -> rusqlite::Result<()> {
+        let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version < 1 {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS downloads (
+                     seq  INTEGER PRIMARY KEY AUTOINCREMENT,
+                     id   INTEGER NOT NULL UNIQUE,
+                     data TEXT NOT NULL
+                 );
+                 CREATE TABLE IF NOT EXISTS queue (
+                     position INTEGER PRIMARY KEY,
+                     id       INTEGER NOT NULL
+                 );
+                 CREATE TABLE IF NOT EXISTS settings (
+                     key   TEXT PRIMARY KEY,
+                     value TEXT NOT NULL
+                 );",
+            )?;
+        }
+        conn.pragma_update(None, "user_version", DB_VERSION)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let any_download: Option<i64> = conn
+            .query_row("SELECT 1 FROM downloads LIMIT 1", [], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let any_setting: Option<i64> = conn
+            .query_row("SELECT 1 FROM settings LIMIT 1", [], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?;
+        Ok(any_download.is_none() && any_setting.is_none())
+    }
+
+    /// Reads everything back in the same JSON shape `sync` accepts, with the
+    /// queue expanded from ids to the matching download objects.
+    pub fn load(&self) -> Result<Value, String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let mut written = Written::default();
+
+        let mut downloads = Vec::new();
+        let mut by_id = HashMap::new();
+        {
+            let mut stmt = conn
+                .prepare("SELECT id, data FROM downloads ORDER BY seq")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, String>(1)?)))
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                let (id, data) = row.map_err(|e| e.to_string())?;
+                let value: Value = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+                by_id.insert(id, value.clone());
+                downloads.push(value);
+                written.downloads.insert(id, data);
+            }
+        }
+
+        let mut queue = Vec::new();
+        {
+            let mut stmt = conn
+                .prepare("SELECT id FROM queue ORDER BY position")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| row.get::<_, i64>(0))
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                let id = row.map_err(|e| e.to_string())? as u64;
+                if let Some(dl) = by_id.get(&id) {
+                    queue.push(dl.clone());
+                }
+                written.queue.push(id);
+            }
+        }
+
+        let mut state = Map::new();
+        {
+            let mut stmt = conn
+                .prepare("SELECT key, value FROM settings")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                let (key, value) = row.map_err(|e| e.to_string())?;
+                state.insert(
+                    key.clone(),
+                    serde_json::from_str(&value).map_err(|e| e.to_string())?,
+                );
+                written.settings.insert(key, value);
+            }
+        }
+
+        state.insert("downloads".to_string(), Value::Array(downloads));
+        state.insert("queue".to_string(), Value::Array(queue));
+        *self.written.lock().map_err(|e| e.to_string())? = written;
+        Ok(Value::Object(state))
+    }
+
+    /// Copies the database to `path`, e.g. to keep one that failed to load.
+    pub fn backup_to(&self, path: &Path) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn mark_changed(&self, id: u64) {
+        if let Ok(mut changes) = self.changes.lock() {
+            changes.ids.insert(id);
+        }
+    }
+
+    /// Makes the next save compare every download.
+    pub fn mark_all_changed(&self) {
+        if let Ok(mut changes) = self.changes.lock() {
+            changes.all = true;
+        }
+    }
+
+    /// Which of the current downloads `ids` the next write needs: those
+    /// marked as changed and those not in the database yet. `None` means all
+    /// of them. Clears the marks.
+    pub fn take_changed(&self, ids: &[u64]) -> Option<HashSet<u64>> {
+        let mut changes = self.changes.lock().ok()?;
+        if changes.all || changes.last_full.elapsed() >= FULL_SYNC_INTERVAL {
+            changes.ids.clear();
+            changes.all = false;
+            changes.last_full = Instant::now();
+            return None;
+        }
+        let written = self.written.lock().ok()?;
+        let mut needed = std::mem::take(&mut changes.ids);
+        needed.extend(ids.iter().filter(|id| !written.downloads.contains_key(id)));
+        Some(needed)
+    }
+
+    /// Writes `state` (a serialized `PersistedState`) in one transaction,
+    /// comparing every download with what the database holds.
+    pub fn sync(&self, state: Value) -> Result<(), String> {
+        let Value::Object(mut state) = state else {
+            return Err("state is not a JSON object".to_string());
+        };
+
+        let rows: Vec<(u64, String)> = match state.remove("downloads") {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|dl| Some((dl.get("id")?.as_u64()?, dl.to_string())))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let ids = rows.iter().map(|(id, _)| *id).collect();
+        let queue: Vec<u64> = match state.remove("queue") {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|dl| dl.get("id")?.as_u64())
+                .collect(),
+            _ => Vec::new(),
+        };
+        self.write(state, ids, rows, queue)
+    }
+
+    /// Writes one save in a single transaction. `settings` holds the
+    /// top-level fields other than `downloads` and `queue`; `ids` are all
+    /// current downloads in order, of which `rows` are the serialized ones
+    /// that may have changed. Rows of downloads not in `ids` are deleted.
+    pub fn write(
+        &self,
+        settings: Map<String, Value>,
+        ids: Vec<u64>,
+        rows: Vec<(u64, String)>,
+        queue: Vec<u64>,
+    ) -> Result<(), String> {
+        let result = self.write_transaction(settings, ids, rows, queue);
+        if result.is_err() {
+            // What was taken for this write must not get lost.
+            self.mark_all_changed();
+        }
+        result
+    }
+
+    fn write_transaction(
+        &self,
+        settings: Map<String, Value>,
+        ids: Vec<u64>,
+        rows: Vec<(u64, String)>,
+        queue: Vec<u64>,
+    ) -> Result<(), String> {
+        let settings: HashMap<String, String> = settings
+            .into_iter()
+            .map(|(key, value)| (key, value.to_string()))
+            .collect();
+        let ids: HashSet<u64> = ids.into_iter().collect();
+
+        let mut written = self.written.lock().map_err(|e| e.to_string())?;
+        let mut conn = self.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        let mut updated = Vec::with_capacity(rows.len());
+        for (id, data) in rows {
+            if written.downloads.get(&id) != Some(&data) {
+                tx.execute(
+                    "INSERT INTO downloads (id, data) VALUES (?1, ?2)
+                     ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+                    params![id as i64, data],
+                )
+                .map_err(|e| e.to_string())?;
+                updated.push((id, data));
+            }
+        }
+        let removed: Vec<u64> = written
+            .downloads
+            .keys()
+            .filter(|id| !ids.contains(id))
+            .copied()
+            .collect();
+        for id in &removed {
+            tx.execute("DELETE FROM downloads WHERE id = ?1", params![*id as i64])
+                .map_err(|e| e.to_string())?;
+        }
+
+        if written.queue != queue {
+            tx.execute("DELETE FROM queue", []).map_err(|e| e.to_string())?;
+            for (position, id) in queue.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO queue (position, id) VALUES (?1, ?2)",
+                    params![position as i64, *id as i64],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+
+        for (key, value) in &settings {
+            if written.settings.get(key) != Some(value) {
+                tx.execute(
+                    "INSERT INTO settings (key, value) VALUES (?1, ?2)
+                     ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                    params![key, value],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+
+        tx.commit().map_err(|e| e.to_string())?;
+        written.downloads.extend(updated);
+        for id in removed {
+            written.downloads.remove(&id);
+        }
+        written.queue = queue;
+        written.settings = settings;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stored(store: &Store, id: u64) -> Option<String> {
+        store
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT data FROM downloads WHERE id = ?1", params![id as i64], |row| row.get(0))
+            .optional()
+            .unwrap()
+    }
+
+    #[test]
+    fn sync_and_load_round_trip() {
+        let store = Store::open_in_memory().unwrap();
+        assert!(store.is_empty().unwrap());
+
+        let a = json!({ "id": 1, "url": "https://example.com/a" });
+        let b = json!({ "id": 2, "url": "https://example.com/b" });
+        store
+            .sync(json!({
+                "speed_limit": 100.0,
+                "downloads": [a.clone(), b.clone()],
+                "queue": [b.clone()],
+            }))
+            .unwrap();
+
+        assert!(!store.is_empty().unwrap());
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded["speed_limit"], json!(100.0));
+        assert_eq!(loaded["downloads"], json!([a, b.clone()]));
+        assert_eq!(loaded["queue"], json!([b]));
+    }
+
+    #[test]
+    fn write_skips_unchanged_rows_and_deletes_removed_ones() {
+        let store = Store::open_in_memory().unwrap();
+        let row = |id: u64, url: &str| (id, json!({ "id": id, "url": url }).to_string());
+        store
+            .write(Map::new(), vec![1, 2], vec![row(1, "a"), row(2, "b")], vec![])
+            .unwrap();
+
+        // Rows the store already wrote are compared with what it wrote, not
+        // with the database, so a change behind its back shows they're skipped.
+        store
+            .conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE downloads SET data = 'untouched' WHERE id = 1", [])
+            .unwrap();
+        store.write(Map::new(), vec![1, 2], vec![row(1, "a")], vec![]).unwrap();
+        assert_eq!(stored(&store, 1).as_deref(), Some("untouched"));
+
+        store.write(Map::new(), vec![1], vec![row(1, "a2")], vec![1]).unwrap();
+        assert_eq!(stored(&store, 1), Some(row(1, "a2").1));
+        assert_eq!(stored(&store, 2), None);
+        assert_eq!(store.load().unwrap()["queue"], json!([{ "id": 1, "url": "a2" }]));
+    }
+
+    #[test]
+    fn take_changed_adds_downloads_not_written_yet() {
+        let store = Store::open_in_memory().unwrap();
+        store
+            .write(Map::new(), vec![1], vec![(1, "{}".to_string())], vec![])
+            .unwrap();
+
+        store.mark_changed(1);
+        assert_eq!(store.take_changed(&[1, 2]), Some(HashSet::from([1, 2])));
+        assert_eq!(store.take_changed(&[1]), Some(HashSet::new()));
+
+        store.mark_all_changed();
+        assert_eq!(store.take_changed(&[1]), None);
+        assert_eq!(store.take_changed(&[1]), Some(HashSet::new()));
+    }
+}
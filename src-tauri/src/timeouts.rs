@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use reqwest::{Client, ClientBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Network timeouts and stall detection for transfers. Zero turns the
+/// corresponding check off.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TimeoutSettings {
+    pub connect_secs: u64,
+    /// Give up on a connection that sends nothing at all for this long.
+    pub read_idle_secs: u64,
+    /// A transfer slower than this for `stall_secs` counts as stalled.
+    pub stall_min_kbps: f64,
+    pub stall_secs: u64,
+}
+
+impl Default for TimeoutSettings {
+    fn default() -> Self {
+        TimeoutSettings {
+            connect_secs: 30,
+            read_idle_secs: 60,
+            stall_min_kbps: 1.0,
+            stall_secs: 120,
+        }
+    }
+}
+
+impl TimeoutSettings {
+    pub fn client(&self) -> Client {
+        self.apply(Client::builder())
+            .build()
+            .unwrap_or_else(|_| Client::new())
+    }
+
+    pub fn apply(&self, builder: ClientBuilder) -> ClientBuilder {
+        if self.connect_secs > 0 {
+            builder.connect_timeout(Duration::from_secs(self.connect_secs))
+        } else {
+            builder
+        }
+    }
+
+    pub fn read_idle(&self) -> Option<Duration> {
+        (self.read_idle_secs > 0).then(|| Duration::from_secs(self.read_idle_secs))
+    }
+
+    /// Stall detection only makes sense when the speed limit allows going
+    /// faster than the threshold.
+    pub fn stall_threshold(&self, speed_limit_kbps: Option<f64>) -> Option<(f64, Duration)> {
+        if self.stall_min_kbps <= 0.0 || self.stall_secs == 0 {
+            return None;
+        }
+        if speed_limit_kbps.is_some_and(|limit| limit > 0.0 && limit <= self.stall_min_kbps) {
+            return None;
+        }
+        Some((self.stall_min_kbps, Duration::from_secs(self.stall_secs)))
+    }
+}
+
+/// Tracks how long throughput has stayed below the stall threshold.
+pub struct StallDetector {
+    threshold: Option<(f64, Duration)>,
+    slow_since: Option<std::time::Instant>,
+}
+
+impl StallDetector {
+    pub fn new(threshold: Option<(f64, Duration)>) -> Self {
+        StallDetector {
+            threshold,
+            slow_since: None,
+        }
+    }
+
+    /// Feeds one speed sample; returns an error message once stalled.
+    pub fn sample(&mut self, kbps: f64) -> Option<String> {
+        let (min_kbps, window) = self.threshold?;
+        if kbps >= min_kbps {
+            self.slow_since = None;
+            return None;
+        }
+        let since = *self.slow_since.get_or_insert_with(std::time::Instant::now);
+        (since.elapsed() >= window).then(|| {
+            format!(
+                "Stalled: below {} KB/s for {} seconds",
+                min_kbps,
+                window.as_secs()
+            )
+        })
+    }
+}
@@ -1 +1,282 @@
-
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// Characters left alone when a file name is put into a web seed URL.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+/// Guards against nesting bombs in malformed files.
+const MAX_DEPTH: usize = 64;
+
+/// A bencoded value. Dictionaries keep their keys in file order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bencode {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(Vec<(Vec<u8>, Bencode)>),
+}
+
+impl Bencode {
+    pub fn get(&self, key: &str) -> Option<&Bencode> {
+        match self {
+            Bencode::Dict(entries) => entries
+                .iter()
+                .find(|(k, _)| k == key.as_bytes())
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Bencode::Bytes(bytes) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Bencode::Int(n) => u64::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes one bencoded value from the start of `data` and returns it with
+/// the rest of the input.
+pub fn decode(data: &[u8]) -> Result<(Bencode, &[u8]), String> {
+    decode_at(data, 0)
+}
+
+fn decode_at(data: &[u8], depth: usize) -> Result<(Bencode, &[u8]), String> {
+    if depth > MAX_DEPTH {
+        return Err("Torrent is nested too deeply".to_string());
+    }
+    match data.first() {
+        Some(b'i') => {
+            let end = data.iter().position(|&b| b == b'e').ok_or("Unterminated integer")?;
+            let n = std::str::from_utf8(&data[1..end])
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or("Invalid integer")?;
+            Ok((Bencode::Int(n), &data[end + 1..]))
+        }
+        Some(b'l') => {
+            let mut rest = &data[1..];
+            let mut items = Vec::new();
+            while rest.first() != Some(&b'e') {
+                let (item, next) = decode_at(rest, depth + 1)?;
+                items.push(item);
+                rest = next;
+            }
+            Ok((Bencode::List(items), &rest[1..]))
+        }
+        Some(b'd') => {
+            let mut rest = &data[1..];
+            let mut entries = Vec::new();
+            while rest.first() != Some(&b'e') {
+                let (key, next) = decode_at(rest, depth + 1)?;
+                let Bencode::Bytes(key) = key else {
+                    return Err("Dictionary key is not a string".to_string());
+                };
+                let (value, next) = decode_at(next, depth + 1)?;
+                entries.push((key, value));
+                rest = next;
+            }
+            Ok((Bencode::Dict(entries), &rest[1..]))
+        }
+        Some(b'0'..=b'9') => {
+            let colon = data.iter().position(|&b| b == b':').ok_or("Invalid string")?;
+            let len: usize = std::str::from_utf8(&data[..colon])
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or("Invalid string length")?;
+            let start = colon + 1;
+            let end = start.checked_add(len).ok_or("Invalid string length")?;
+            let bytes = data.get(start..end).ok_or("Truncated string")?;
+            Ok((Bencode::Bytes(bytes.to_vec()), &data[end..]))
+        }
+        Some(_) => Err("Invalid bencode".to_string()),
+        None => Err("Truncated torrent".to_string()),
+    }
+}
+
+/// A file of a torrent that can be fetched over HTTP from its web seeds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebSeedFile {
+    /// Path inside the torrent, e.g. `["album", "cd1", "01.flac"]`. Components
+    /// that could leave the download folder are dropped.
+    pub path: Vec<String>,
+    pub size: Option<u64>,
+    /// One URL per web seed, in the torrent's order.
+    pub urls: Vec<String>,
+}
+
+/// `name` as a single path component. `:` goes too, or `C:x` would become
+/// a drive prefix on Windows.
+fn safe_component(name: &str) -> Option<String> {
+    let name = name.replace(['/', '\\', ':'], "_");
+    (!name.is_empty() && name != "." && name != "..").then_some(name)
+}
+
+/// The files of a `.torrent` with their HTTP web seed URLs (BEP 19,
+/// `url-list`). There is no BitTorrent engine, so a torrent without web
+/// seeds can't be downloaded and yields an error.
+pub fn web_seed_files(data: &[u8]) -> Result<Vec<WebSeedFile>, String> {
+    let (torrent, _) = decode(data)?;
+    let info = torrent.get("info").ok_or("Torrent has no info dictionary")?;
+    // Names as in the torrent go into the URLs, `safe_component` ones into
+    // the local path.
+    let raw_name = info
+        .get("name.utf-8")
+        .or_else(|| info.get("name"))
+        .and_then(Bencode::as_str)
+        .ok_or("Torrent has no name")?;
+    let name = safe_component(raw_name).ok_or("Torrent has no name")?;
+
+    let seeds: Vec<&str> = match torrent.get("url-list") {
+        Some(Bencode::List(items)) => items.iter().filter_map(Bencode::as_str).collect(),
+        Some(single) => single.as_str().into_iter().collect(),
+        None => Vec::new(),
+    };
+    let seeds: Vec<&str> = seeds
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| s.starts_with("http://") || s.starts_with("https://"))
+        .collect();
+    if seeds.is_empty() {
+        return Err("Torrent has no HTTP web seeds".to_string());
+    }
+
+    let Some(Bencode::List(entries)) = info.get("files") else {
+        // Single file: a seed ending in `/` is a folder holding it.
+        let urls = seeds
+            .iter()
+            .map(|seed| {
+                if seed.ends_with('/') {
+                    format!("{}{}", seed, utf8_percent_encode(raw_name, SEGMENT))
+                } else {
+                    seed.to_string()
+                }
+            })
+            .collect();
+        return Ok(vec![WebSeedFile {
+            path: vec![name],
+            size: info.get("length").and_then(Bencode::as_u64),
+            urls,
+        }]);
+    };
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let Some(Bencode::List(parts)) = entry.get("path.utf-8").or_else(|| entry.get("path")) else {
+            continue;
+        };
+        let mut raw_path = vec![raw_name];
+        let mut path = vec![name.clone()];
+        for part in parts.iter().filter_map(Bencode::as_str) {
+            if let Some(safe) = safe_component(part) {
+                raw_path.push(part);
+                path.push(safe);
+            }
+        }
+        if path.len() < 2 {
+            continue;
+        }
+        let encoded: Vec<String> = raw_path
+            .iter()
+            .map(|p| utf8_percent_encode(p, SEGMENT).to_string())
+            .collect();
+        let urls = seeds
+            .iter()
+            .map(|seed| format!("{}/{}", seed.trim_end_matches('/'), encoded.join("/")))
+            .collect();
+        files.push(WebSeedFile {
+            path,
+            size: entry.get("length").and_then(Bencode::as_u64),
+            urls,
+        });
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(s: &str) -> Bencode {
+        Bencode::Bytes(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn decode_values() {
+        let (value, rest) = decode(b"d3:agei-42e4:tagsl1:a0:ee!").unwrap();
+        assert_eq!(rest, b"!");
+        assert_eq!(
+            value,
+            Bencode::Dict(vec![
+                (b"age".to_vec(), Bencode::Int(-42)),
+                (b"tags".to_vec(), Bencode::List(vec![bytes("a"), bytes("")])),
+            ])
+        );
+        assert_eq!(value.get("age").and_then(Bencode::as_u64), None);
+        assert_eq!(value.get("tags").and_then(|t| t.get("a")), None);
+    }
+
+    #[test]
+    fn decode_rejects_malformed_input() {
+        for data in [
+            &b""[..],
+            b"i12",
+            b"ixe",
+            b"5:abc",
+            b"99999999999999999999999:x",
+            b"l1:a",
+            b"di1e1:ae",
+            b"x",
+        ] {
+            assert!(decode(data).is_err(), "{:?}", String::from_utf8_lossy(data));
+        }
+        let nested = "l".repeat(MAX_DEPTH + 2);
+        assert!(decode(nested.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn single_file_web_seeds() {
+        let data = b"d8:url-listl20:https://a.example/d/17:ftp://b.example/x21:http://c.example/filee\
+                     4:infod6:lengthi10e4:name7:a b.isoee";
+        let files = web_seed_files(data).unwrap();
+        assert_eq!(
+            files,
+            vec![WebSeedFile {
+                path: vec!["a b.iso".to_string()],
+                size: Some(10),
+                urls: vec![
+                    "https://a.example/d/a%20b.iso".to_string(),
+                    "http://c.example/file".to_string(),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn multi_file_paths_are_sanitized() {
+        let data = b"d8:url-list18:https://a.example/\
+                     4:infod4:name5:Album5:filesl\
+                     d6:lengthi1e4:pathl3:cd17:a:b.txtee\
+                     d6:lengthi2e4:pathl2:..ee\
+                     d6:lengthi3e4:pathl2:..5:x.txtee\
+                     eee";
+        let files = web_seed_files(data).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, vec!["Album", "cd1", "a_b.txt"]);
+        assert_eq!(files[0].urls, vec!["https://a.example/Album/cd1/a%3Ab.txt"]);
+        assert_eq!(files[1].path, vec!["Album", "x.txt"]);
+        assert_eq!(files[1].urls, vec!["https://a.example/Album/x.txt"]);
+        assert_eq!(files[1].size, Some(3));
+    }
+
+    #[test]
+    fn torrents_without_web_seeds_fail() {
+        assert!(web_seed_files(b"d4:infod4:name1:a6:lengthi1eee").is_err());
+        assert!(web_seed_files(b"d8:url-list18:https://a.example/e").is_err());
+    }
+}
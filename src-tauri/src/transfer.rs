@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::events::DownloadEvent;
+use crate::headers::RequestHeaders;
+use crate::persistence::{self, save_state, PersistedState, SCHEMA_VERSION};
+use crate::{
+    check_download_dir, check_feed_url, check_watch_folders, check_webhooks, create_download, file_name_only,
+    new_download_id, now_secs, AppState, Download, DownloadStatus, EnqueueOptions,
+};
+
+/// Version of the JSON export layout, see `DownloadsExport`.
+const EXPORT_VERSION: u32 = 1;
+const CSV_COLUMNS: [&str; 11] = [
+    "id",
+    "url",
+    "file_path",
+    "status",
+    "category",
+    "downloaded_bytes",
+    "total_bytes",
+    "created_at",
+    "completed_at",
+    "mirrors",
+    "referer",
+];
+/// Settings that are lists of independent entries. Merging a profile adds
+/// its entries to these instead of replacing them.
+const LIST_SETTINGS: [&str; 4] = ["post_actions", "webhooks", "feeds", "watch_folders"];
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl TransferFormat {
+    /// `Csv` for a `.csv` file, `Json` otherwise.
+    pub fn of_path(path: &Path) -> TransferFormat {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => TransferFormat::Csv,
+            _ => TransferFormat::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Keep what is there and add what is new.
+    #[default]
+    Merge,
+    /// Drop the current list (or setting) first. Files on disk are kept.
+    Replace,
+}
+
+/// The JSON export of the download list. The exporting machine's download
+/// folder is recorded for reference; imports keep only the file names.
+#[derive(Serialize, Deserialize)]
+struct DownloadsExport {
+    version: u32,
+    exported_at: u64,
+    download_dir: PathBuf,
+    downloads: Vec<Download>,
+}
+
+/// What an import did. `ids` maps each imported entry's old id to its new one.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    pub ids: HashMap<u64, u64>,
+    pub errors: Vec<String>,
+}
+
+/// Whether `download` has one of `statuses`. No filter keeps everything.
+fn status_matches(download: &Download, statuses: &[String]) -> bool {
+    statuses.is_empty()
+        || statuses
+            .iter()
+            .any(|s| s.trim().eq_ignore_ascii_case(download.status.name()))
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Rows of `text`, RFC 4180 style: quoted fields may hold commas, quotes
+/// (doubled) and line breaks.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    rows
+}
+
+/// The download list, or the part of it with one of `statuses`, as JSON or
+/// CSV. CSV carries the essentials only; JSON round-trips everything.
+pub async fn export_downloads(
+    state: &AppState,
+    format: TransferFormat,
+    statuses: &[String],
+) -> Result<String, String> {
+    let downloads: Vec<Download> = state
+        .downloads
+        .lock()
+        .await
+        .iter()
+        .filter(|d| status_matches(d, statuses))
+        .cloned()
+        .collect();
+
+    match format {
+        TransferFormat::Json => {
+            let export = DownloadsExport {
+                version: EXPORT_VERSION,
+                exported_at: now_secs(),
+                download_dir: state.download_dir.lock().await.clone(),
+                downloads,
+            };
+            serde_json::to_string_pretty(&export).map_err(|e| e.to_string())
+        }
+        TransferFormat::Csv => {
+            let mut out = CSV_COLUMNS.join(",") + "\n";
+            for d in &downloads {
+                let row = [
+                    d.id.to_string(),
+                    d.url.clone(),
+                    d.file_path.to_string_lossy().into_owned(),
+                    d.status.name().to_string(),
+                    d.category.clone(),
+                    d.downloaded_bytes.to_string(),
+                    optional(d.total_bytes),
+                    optional(d.created_at),
+                    optional(d.completed_at),
+                    d.mirrors.join(" "),
+                    d.headers.referer.clone().unwrap_or_default(),
+                ];
+                let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+                out.push_str(&fields.join(","));
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// With `Replace`, empties the download list and queue first. Refused while
+/// anything is downloading, since those transfers would lose their entries.
+async fn clear_for_replace(state: &AppState) -> Result<(), String> {
+    if !state.active_downloads.lock().await.is_empty() {
+        return Err("Pause or finish the running downloads before replacing the list".to_string());
+    }
+    let removed: Vec<u64> = state.downloads.lock().await.drain(..).map(|d| d.id).collect();
+    state.queue.lock().await.clear();
+    for id in removed {
+        state.events.emit(DownloadEvent::Removed { id });
+    }
+    Ok(())
+}
+
+/// Adds full download records from a JSON export under new ids. Unfinished
+/// ones come in paused, to be resumed by hand. Every file is placed directly
+/// in this machine's download folder under its own name, and what would act
+/// on it later (post-download actions, auto-delete, extraction) is dropped.
+/// With `Merge`, records whose file is already in the list are skipped.
+async fn import_records(state: &AppState, export: DownloadsExport, report: &mut ImportReport) {
+    let download_dir = state.download_dir.lock().await.clone();
+    let mut downloads = state.downloads.lock().await;
+    let before = downloads.len();
+    add_records(&mut downloads, &download_dir, export.downloads, report);
+    for download in &downloads[before..] {
+        state.events.emit(DownloadEvent::Added { download: download.clone() });
+    }
+}
+
+/// Appends `records` to `downloads` as `import_records` describes.
+fn add_records(downloads: &mut Vec<Download>, download_dir: &Path, records: Vec<Download>, report: &mut ImportReport) {
+    for mut download in records {
+        let name = download
+            .file_path
+            .file_name()
+            .and_then(|n| file_name_only(&n.to_string_lossy()));
+        let Some(name) = name else {
+            report.errors.push(format!("{}: no file name", download.url));
+            continue;
+        };
+        download.file_path = download_dir.join(name);
+        download.post_actions = None;
+        download.action_log.clear();
+        download.delete_after = None;
+        download.extraction = None;
+        if downloads.iter().any(|d| d.file_path == download.file_path) {
+            report.skipped += 1;
+            continue;
+        }
+        let old_id = download.id;
+        download.id = new_download_id(downloads);
+        if download.status != DownloadStatus::Completed {
+            download.status = DownloadStatus::Paused;
+        }
+        download.speed_kbps = 0.0;
+        download.eta_seconds = None;
+        download.next_retry_at = None;
+
+        report.ids.insert(old_id, download.id);
+        report.imported += 1;
+        downloads.push(download);
+    }
+}
+
+/// Enqueues the rows of a CSV export (without starting them) from their
+/// `url`, plus `file_path`'s file name, `category`, `mirrors` and `referer`
+/// when present. With `Merge`, rows whose URL is already in the list are
+/// skipped.
+async fn import_rows(
+    state: &AppState,
+    app_handle: &AppHandle,
+    text: &str,
+    report: &mut ImportReport,
+) -> Result<(), String> {
+    let mut rows = parse_csv(text).into_iter();
+    let header: Vec<String> = rows
+        .next()
+        .ok_or("CSV file is empty")?
+        .iter()
+        .map(|h| h.trim().to_ascii_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let url_column = column("url").ok_or("CSV file has no url column")?;
+    let (id_column, path_column) = (column("id"), column("file_path"));
+    let (category_column, mirrors_column, referer_column) =
+        (column("category"), column("mirrors"), column("referer"));
+
+    for row in rows {
+        let get = |index: Option<usize>| {
+            index
+                .and_then(|i| row.get(i))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let Some(url) = get(Some(url_column)) else {
+            report.skipped += 1;
+            continue;
+        };
+        if state.downloads.lock().await.iter().any(|d| d.url == url) {
+            report.skipped += 1;
+            continue;
+        }
+        let options = EnqueueOptions {
+            save_as: get(path_column).and_then(|p| {
+                // The folder belongs to the other machine; only the name is kept.
+                p.rsplit(['/', '\\']).next().map(str::to_string)
+            }),
+            mirrors: get(mirrors_column)
+                .map(|m| m.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default(),
+            category: get(category_column),
+            headers: RequestHeaders {
+                referer: get(referer_column),
+                ..RequestHeaders::default()
+            },
+            ..EnqueueOptions::default()
+        };
+        match create_download(url.clone(), options, state, false, app_handle.clone()).await {
+            Ok(id) => {
+                if let Some(old_id) = get(id_column).and_then(|i| i.parse().ok()) {
+                    report.ids.insert(old_id, id);
+                }
+                report.imported += 1;
+            }
+            Err(e) => report.errors.push(format!("{}: {}", url, e)),
+        }
+    }
+    Ok(())
+}
+
+/// Imports a download list written by `export_downloads` (or any CSV with a
+/// `url` column). Every entry gets a new id; see `ImportReport::ids`.
+pub async fn import_downloads(
+    state: &AppState,
+    app_handle: &AppHandle,
+    text: &str,
+    format: TransferFormat,
+    mode: ImportMode,
+) -> Result<ImportReport, String> {
+    let mut report = ImportReport::default();
+    match format {
+        TransferFormat::Json => {
+            // A bare list of downloads is taken as well as the full export.
+            let export = match serde_json::from_str::<DownloadsExport>(text) {
+                Ok(export) => export,
+                Err(e) => match serde_json::from_str::<Vec<Download>>(text) {
+                    Ok(downloads) => DownloadsExport {
+                        version: EXPORT_VERSION,
+                        exported_at: 0,
+                        download_dir: PathBuf::new(),
+                        downloads,
+                    },
+                    Err(_) => return Err(format!("Invalid download export: {}", e)),
+                },
+            };
+            if export.version > EXPORT_VERSION {
+                return Err(format!(
+                    "Export version {} is newer than this build understands",
+                    export.version
+                ));
+            }
+            if mode == ImportMode::Replace {
+                clear_for_replace(state).await?;
+            }
+            import_records(state, export, &mut report).await;
+        }
+        TransferFormat::Csv => {
+            if mode == ImportMode::Replace {
+                clear_for_replace(state).await?;
+            }
+            import_rows(state, app_handle, text, &mut report).await?;
+        }
+    }
+    save_state(app_handle, state).await;
+    Ok(report)
+}
+
+/// Every setting, without the download list: a profile that can be loaded
+/// on another machine with `import_settings`. Without `with_secrets`, the
+/// webhook signing secrets are left out.
+pub async fn export_settings(state: &AppState, with_secrets: bool) -> Result<Value, String> {
+    let mut value = serde_json::to_value(persistence::snapshot(state).await).map_err(|e| e.to_string())?;
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("downloads");
+        obj.remove("queue");
+        if !with_secrets {
+            if let Some(Value::Array(webhooks)) = obj.get_mut("webhooks") {
+                for webhook in webhooks.iter_mut().filter_map(Value::as_object_mut) {
+                    webhook.remove("secret");
+                }
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// Whether two entries of list setting `key` are the same thing: feeds and
+/// webhooks by URL, watch folders by path, anything else when equal.
+fn same_entry(key: &str, a: &Value, b: &Value) -> bool {
+    let field = match key {
+        "feeds" | "webhooks" => "url",
+        "watch_folders" => "path",
+        _ => return a == b,
+    };
+    a.get(field).is_some() && a.get(field) == b.get(field)
+}
+
+/// Adds the entries of `incoming` that `current` doesn't have yet. Merged
+/// feeds get new ids after the existing ones.
+fn merge_list(key: &str, current: &mut Vec<Value>, incoming: Vec<Value>) {
+    let mut next_feed_id = current
+        .iter()
+        .filter_map(|f| f.get("id").and_then(Value::as_u64))
+        .max()
+        .unwrap_or(0)
+        + 1;
+    for mut entry in incoming {
+        if current.iter().any(|c| same_entry(key, c, &entry)) {
+            continue;
+        }
+        if key == "feeds" {
+            if let Some(obj) = entry.as_object_mut() {
+                obj.insert("id".to_string(), Value::from(next_feed_id));
+                next_feed_id += 1;
+            }
+        }
+        current.push(entry);
+    }
+}
+
+/// Loads a settings profile. Settings missing from the profile stay as they
+/// are. With `Merge`, list settings (post-download actions, webhooks, feeds,
+/// watch folders) gain the profile's new entries; with `Replace` they are
+/// swapped for the profile's like every other setting. The result must pass
+/// the checks the setters make.
+pub async fn import_settings(
+    state: &AppState,
+    app_handle: &AppHandle,
+    profile: Value,
+    mode: ImportMode,
+) -> Result<(), String> {
+    let Value::Object(incoming) = profile else {
+        return Err("Settings profile is not a JSON object".to_string());
+    };
+    let version = incoming.get("schema_version").and_then(Value::as_u64).unwrap_or(0);
+    if version > SCHEMA_VERSION as u64 {
+        return Err(format!(
+            "Settings profile has schema version {}, this build only understands up to {}",
+            version, SCHEMA_VERSION
+        ));
+    }
+    let current = serde_json::to_value(persistence::snapshot(state).await).map_err(|e| e.to_string())?;
+    let Value::Object(mut current_obj) = current else {
+        return Err("Failed to read the current settings".to_string());
+    };
+
+    for (key, value) in incoming {
+        if matches!(key.as_str(), "schema_version" | "downloads" | "queue") {
+            continue;
+        }
+        match (mode, current_obj.get_mut(&key), value) {
+            (ImportMode::Merge, Some(Value::Array(list)), Value::Array(entries))
+                if LIST_SETTINGS.contains(&key.as_str()) =>
+            {
+                merge_list(&key, list, entries);
+            }
+            (_, _, value) => {
+                current_obj.insert(key, value);
+            }
+        }
+    }
+
+    let merged: PersistedState = serde_json::from_value(Value::Object(current_obj))
+        .map_err(|e| format!("Invalid settings profile: {}", e))?;
+    // The same checks the setters make.
+    check_download_dir(&merged.download_dir)
+        .map_err(|e| format!("Download folder {}: {}", merged.download_dir.display(), e))?;
+    check_webhooks(&merged.webhooks)?;
+    for feed in &merged.feeds {
+        check_feed_url(&feed.url)?;
+    }
+    check_watch_folders(&merged.watch_folders)?;
+    persistence::apply_settings(state, merged).await;
+    state.events.emit(DownloadEvent::settings_changed("profile"));
+    save_state(app_handle, state).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: u64, path: &str, status: &str) -> Download {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "url": format!("https://example.com/{}", id),
+            "file_path": path,
+            "progress": 0.0,
+            "status": status,
+            "downloaded_bytes": 0,
+            "speed_kbps": 0.0,
+            "retries_left": 0,
+            "post_actions": [{ "type": "run_command", "command": "rm -rf ~" }],
+            "delete_after": 1,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_csv_handles_quotes_and_line_breaks() {
+        let text = "\u{feff}url,name\r\nhttps://a,\"b, \"\"c\"\"\"\n\n , \nhttps://d,\"two\nlines\"";
+        assert_eq!(
+            parse_csv(text),
+            vec![
+                vec!["url", "name"],
+                vec!["https://a", "b, \"c\""],
+                vec!["https://d", "two\nlines"],
+            ]
+        );
+        assert!(parse_csv("").is_empty());
+    }
+
+    #[test]
+    fn csv_field_quotes_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        let row = [csv_field("x,y"), csv_field("line\nbreak")].join(",");
+        assert_eq!(parse_csv(&row), vec![vec!["x,y", "line\nbreak"]]);
+    }
+
+    #[test]
+    fn add_records_remaps_ids_and_places_files() {
+        let dir = Path::new("/downloads");
+        let mut downloads = vec![record(7, "/downloads/taken.iso", "completed")];
+        let records = vec![
+            record(7, "/other/machine/new.iso", "downloading"),
+            record(8, "/elsewhere/taken.iso", "completed"),
+            record(9, "/other/machine/done.zip", "completed"),
+            record(10, "/", "paused"),
+        ];
+        let mut report = ImportReport::default();
+        add_records(&mut downloads, dir, records, &mut report);
+
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(downloads.len(), 3);
+        let new_iso = downloads.iter().find(|d| d.id == report.ids[&7]).unwrap();
+        assert_eq!(new_iso.file_path, dir.join("new.iso"));
+        assert_eq!(new_iso.status, DownloadStatus::Paused);
+        assert_eq!(new_iso.post_actions, None);
+        assert_eq!(new_iso.delete_after, None);
+        let done = downloads.iter().find(|d| d.id == report.ids[&9]).unwrap();
+        assert_eq!(done.status, DownloadStatus::Completed);
+
+        let mut ids: Vec<u64> = downloads.iter().map(|d| d.id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+    }
+}
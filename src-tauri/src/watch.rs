@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::persistence::save_state;
+use crate::{create_download, enqueue_sources, metalink, torrent, unique_file_path, AppState, EnqueueOptions};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const PROCESSED_DIR: &str = "processed";
+const FAILED_DIR: &str = "failed";
+
+/// A folder other programs drop link lists and job files into.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WatchFolder {
+    pub path: PathBuf,
+    pub enabled: bool,
+    /// Category for what is enqueued from here, unless a job sets its own.
+    /// Empty picks one from the file extension.
+    pub category: String,
+}
+
+impl Default for WatchFolder {
+    fn default() -> Self {
+        WatchFolder {
+            path: PathBuf::new(),
+            enabled: true,
+            category: String::new(),
+        }
+    }
+}
+
+/// One download in a `.json` job file, which holds a single job or a list.
+/// Takes the same options as `POST /add`.
+#[derive(Debug, Deserialize)]
+struct Job {
+    url: String,
+    #[serde(flatten)]
+    options: EnqueueOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JobFile {
+    Many(Vec<Job>),
+    One(Job),
+}
+
+/// What came of one dropped file.
+#[derive(Default)]
+struct Outcome {
+    enqueued: usize,
+    errors: Vec<String>,
+}
+
+impl Outcome {
+    fn record(&mut self, what: &str, result: Result<Option<u64>, String>) {
+        match result {
+            Ok(Some(_)) => self.enqueued += 1,
+            Ok(None) => {}
+            Err(e) => self.errors.push(format!("{}: {}", what, e)),
+        }
+    }
+}
+
+/// Whether `path` is a file this module knows how to pick up.
+fn is_droppable(path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    !hidden && matches!(extension.as_str(), "txt" | "torrent" | "metalink" | "meta4" | "json")
+}
+
+/// Enqueues everything in a dropped file. Errors that concern the whole
+/// file (unreadable, not parseable) come back as `Err`; per-entry ones are
+/// collected in the `Outcome`.
+async fn process_file(
+    path: &Path,
+    folder: &WatchFolder,
+    state: &AppState,
+    app_handle: &AppHandle,
+) -> Result<Outcome, String> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let data = tokio::fs::read(path).await.map_err(|e| e.to_string())?;
+    let base = EnqueueOptions {
+        category: Some(folder.category.clone()),
+        ..EnqueueOptions::default()
+    };
+    let mut outcome = Outcome::default();
+
+    match extension.as_str() {
+        "txt" => {
+            // One URL per line; blank lines and `#` comments are skipped.
+            for line in String::from_utf8_lossy(&data).lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let result = create_download(line.to_string(), base.clone(), state, true, app_handle.clone()).await;
+                outcome.record(line, result.map(Some));
+            }
+        }
+        "metalink" | "meta4" => {
+            for file in metalink::parse(&String::from_utf8_lossy(&data))? {
+                let options = EnqueueOptions {
+                    save_as: Some(file.name.clone()),
+                    ..base.clone()
+                };
+                let result = enqueue_sources(file.urls, file.size, options, state, app_handle.clone()).await;
+                outcome.record(&file.name, result);
+            }
+        }
+        "torrent" => {
+            for file in torrent::web_seed_files(&data)? {
+                let (name, dirs) = file.path.split_last().ok_or("Empty path")?;
+                let options = EnqueueOptions {
+                    save_as: Some(name.clone()),
+                    subdir: (!dirs.is_empty()).then(|| dirs.iter().collect()),
+                    ..base.clone()
+                };
+                let result = enqueue_sources(file.urls, file.size, options, state, app_handle.clone()).await;
+                outcome.record(&file.path.join("/"), result);
+            }
+        }
+        "json" => {
+            let jobs = match serde_json::from_slice(&data).map_err(|e| format!("Invalid job file: {}", e))? {
+                JobFile::Many(jobs) => jobs,
+                JobFile::One(job) => vec![job],
+            };
+            for mut job in jobs {
+                if job.options.category.as_deref().is_none_or(|c| c.trim().is_empty()) {
+                    job.options.category = base.category.clone();
+                }
+                let result = create_download(job.url.clone(), job.options, state, true, app_handle.clone()).await;
+                outcome.record(&job.url, result.map(Some));
+            }
+        }
+        _ => {}
+    }
+    Ok(outcome)
+}
+
+/// Moves a handled file into `processed/`, or into `failed/` together with
+/// a `<name>.log` listing what went wrong. Entries that did get enqueued
+/// stay queued either way. Returns whether the file was moved.
+async fn file_away(path: &Path, folder: &Path, errors: &[String]) -> bool {
+    let target_dir = folder.join(if errors.is_empty() { PROCESSED_DIR } else { FAILED_DIR });
+    if let Err(e) = tokio::fs::create_dir_all(&target_dir).await {
+        eprintln!("Watch folder: cannot create {}: {}", target_dir.display(), e);
+        return false;
+    }
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+    let target = unique_file_path(&target_dir.join(name), &[]);
+    if let Err(e) = tokio::fs::rename(path, &target).await {
+        eprintln!("Watch folder: cannot move {}: {}", path.display(), e);
+        return false;
+    }
+    if !errors.is_empty() {
+        let mut log = target.as_os_str().to_owned();
+        log.push(".log");
+        let _ = tokio::fs::write(PathBuf::from(log), errors.join("\n") + "\n").await;
+    }
+    true
+}
+
+/// The droppable files in `dir` with their size and modification time.
+fn scan(dir: &Path) -> Vec<(PathBuf, (u64, SystemTime))> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let meta = e.metadata().ok().filter(|m| m.is_file())?;
+            let stamp = (meta.len(), meta.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+            Some((e.path(), stamp))
+        })
+        .filter(|(path, _)| is_droppable(path))
+        .collect()
+}
+
+/// Polls the enabled watch folders. A file is only picked up once its size
+/// and modification time stayed the same between two polls, so one that is
+/// still being written is left alone.
+pub fn spawn_folder_watcher(app_handle: AppHandle) {
+    tokio::spawn(async move {
+        let state = app_handle.state::<AppState>().inner().clone();
+        let mut last_seen: HashMap<PathBuf, (u64, SystemTime)> = HashMap::new();
+        // Handled files that couldn't be moved away, so they aren't enqueued
+        // again every poll.
+        let mut stuck: HashMap<PathBuf, (u64, SystemTime)> = HashMap::new();
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let folders: Vec<WatchFolder> = state
+                .watch_folders
+                .lock()
+                .await
+                .iter()
+                .filter(|f| f.enabled && !f.path.as_os_str().is_empty())
+                .cloned()
+                .collect();
+
+            let mut current = HashMap::new();
+            let mut changed = false;
+            for folder in &folders {
+                let dir = folder.path.clone();
+                let files = tokio::task::spawn_blocking(move || scan(&dir)).await.unwrap_or_default();
+                for (path, stamp) in files {
+                    if stuck.get(&path) == Some(&stamp) {
+                        current.insert(path, stamp);
+                        continue;
+                    }
+                    if last_seen.get(&path) != Some(&stamp) {
+                        current.insert(path, stamp);
+                        continue;
+                    }
+                    let errors = match process_file(&path, folder, &state, &app_handle).await {
+                        Ok(outcome) => {
+                            changed |= outcome.enqueued > 0;
+                            outcome.errors
+                        }
+                        Err(e) => vec![e],
+                    };
+                    for error in &errors {
+                        eprintln!("Watch folder: {}: {}", path.display(), error);
+                    }
+                    if !file_away(&path, &folder.path, &errors).await {
+                        stuck.insert(path, stamp);
+                    }
+                }
+            }
+            stuck.retain(|path, _| current.contains_key(path));
+            last_seen = current;
+            if changed {
+                save_state(&app_handle, &state).await;
+            }
+        }
+    });
+}
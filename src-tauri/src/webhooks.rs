@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tauri::{AppHandle, Manager};
+
+use crate::events::{DownloadEvent, QueueEmptyWatch};
+use crate::retry::{self, RetryPolicy};
+use crate::{now_secs, AppState};
+
+const EVENT_HEADER: &str = "X-MadByte-Event";
+const SIGNATURE_HEADER: &str = "X-MadByte-Signature";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    Completed,
+    Failed,
+    /// Nothing left queued or running.
+    QueueEmpty,
+}
+
+impl WebhookEvent {
+    fn name(self) -> &'static str {
+        match self {
+            WebhookEvent::Completed => "completed",
+            WebhookEvent::Failed => "failed",
+            WebhookEvent::QueueEmpty => "queue_empty",
+        }
+    }
+}
+
+/// An endpoint that gets a JSON POST for each of `events`. With a `secret`
+/// the body is signed: `X-MadByte-Signature: sha256=<hex HMAC-SHA256>`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Webhook {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub secret: Option<String>,
+    pub enabled: bool,
+}
+
+impl Default for Webhook {
+    fn default() -> Self {
+        Webhook {
+            url: String::new(),
+            events: vec![WebhookEvent::Completed, WebhookEvent::Failed],
+            secret: None,
+            enabled: true,
+        }
+    }
+}
+
+fn delivery_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 4,
+        base_delay_ms: 1_000,
+        max_delay_ms: 30_000,
+        ..RetryPolicy::default()
+    }
+}
+
+fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+/// One POST. `Err` carries whether trying again could help and the
+/// server's `Retry-After`, if any.
+async fn post(
+    client: &Client,
+    webhook: &Webhook,
+    event: &str,
+    body: &[u8],
+) -> Result<u16, (String, bool, Option<Duration>)> {
+    let mut request = client
+        .post(&webhook.url)
+        .timeout(REQUEST_TIMEOUT)
+        .header("Content-Type", "application/json")
+        .header(EVENT_HEADER, event)
+        .body(body.to_vec());
+    if let Some(secret) = webhook.secret.as_deref().filter(|s| !s.is_empty()) {
+        request = request.header(SIGNATURE_HEADER, signature(secret, body));
+    }
+
+    match request.send().await {
+        Ok(resp) if resp.status().is_success() => Ok(resp.status().as_u16()),
+        Ok(resp) => {
+            let status = resp.status().as_u16();
+            let retryable = delivery_policy().is_retryable_status(status);
+            Err((
+                format!("{} returned {}", webhook.url, resp.status()),
+                retryable,
+                retry::retry_after(resp.headers()),
+            ))
+        }
+        Err(e) => Err((e.to_string(), true, None)),
+    }
+}
+
+/// Delivers `payload`, retrying with backoff on connection errors and
+/// retryable statuses.
+async fn deliver(client: Client, webhook: Webhook, event: &'static str, payload: Value) {
+    let body = payload.to_string().into_bytes();
+    let policy = delivery_policy();
+    let mut attempt = 0;
+    loop {
+        match post(&client, &webhook, event, &body).await {
+            Ok(_) => return,
+            Err((message, retryable, retry_after)) => {
+                attempt += 1;
+                if !retryable || attempt > policy.max_attempts {
+                    eprintln!("Webhook {} failed for {}: {}", webhook.url, event, message);
+                    return;
+                }
+                tokio::time::sleep(policy.delay(attempt, retry_after)).await;
+            }
+        }
+    }
+}
+
+fn payload(event: WebhookEvent, data: Value) -> Value {
+    let mut payload = json!({ "event": event.name(), "at": now_secs() });
+    if let (Value::Object(target), Value::Object(data)) = (&mut payload, data) {
+        target.extend(data);
+    }
+    payload
+}
+
+async fn dispatch(app_handle: &AppHandle, client: &Client, event: WebhookEvent, data: Value) {
+    let state = app_handle.state::<AppState>();
+    let webhooks = state.webhooks.lock().await.clone();
+    let payload = payload(event, data);
+    for webhook in webhooks {
+        if webhook.enabled && webhook.events.contains(&event) {
+            tokio::spawn(deliver(client.clone(), webhook, event.name(), payload.clone()));
+        }
+    }
+}
+
+/// Turns bus events into webhook deliveries. `queue_empty` fires once each
+/// time the last queued or running download finishes.
+pub fn spawn_webhook_dispatcher(app_handle: AppHandle) {
+    let state = app_handle.state::<AppState>().inner().clone();
+    let mut events = Box::pin(state.events.stream());
+    tokio::spawn(async move {
+        let client = Client::new();
+        let mut queue_watch = QueueEmptyWatch::default();
+        while let Some(event) = events.next().await {
+            match &event {
+                DownloadEvent::Completed { download } => {
+                    let data = json!({ "download": download });
+                    dispatch(&app_handle, &client, WebhookEvent::Completed, data).await;
+                }
+                DownloadEvent::Failed { download } => {
+                    let data = json!({ "download": download });
+                    dispatch(&app_handle, &client, WebhookEvent::Failed, data).await;
+                }
+                _ => {}
+            }
+
+            if queue_watch.observe(&event, &state).await {
+                dispatch(&app_handle, &client, WebhookEvent::QueueEmpty, json!({})).await;
+            }
+        }
+    });
+}
+
+/// Sends a single `test` event to `webhook` right away, without retries, and
+/// returns the HTTP status it answered with.
+pub async fn test_fire(webhook: &Webhook) -> Result<u16, String> {
+    let payload = json!({
+        "event": "test",
+        "at": now_secs(),
+        "message": "Webhook test from MadByte",
+    });
+    post(&Client::new(), webhook, "test", payload.to_string().as_bytes())
+        .await
+        .map_err(|(message, _, _)| message)
+}
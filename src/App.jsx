@@ -7,6 +7,17 @@ import DownloadPathInput from "./Components/DownloadPathInput.jsx";
 import {openFile, openFolder} from "./utils/Opener.js";
 import RetrySettings from "./Components/RetrySettings.jsx";
 import AutoStartToggle from "./Components/AutostartToggle.jsx";
+import ConflictPolicySelect from "./Components/ConflictPolicySelect.jsx";
+import DiskSpaceSettings from "./Components/DiskSpaceSettings.jsx";
+import TimeoutSettings from "./Components/TimeoutSettings.jsx";
+import NotificationSettings from "./Components/NotificationSettings.jsx";
+import ClipboardSettings from "./Components/ClipboardSettings.jsx";
+import ResolverSettings from "./Components/ResolverSettings.jsx";
+import ScriptSettings from "./Components/ScriptSettings.jsx";
+import FeedSettings from "./Components/FeedSettings.jsx";
+import WatchFolderSettings from "./Components/WatchFolderSettings.jsx";
+import ImportExportSettings from "./Components/ImportExportSettings.jsx";
+import SiteCrawl from "./Components/SiteCrawl.jsx";
 import glass from './uis/glass.js';
 import material from './uis/material.js';
 import brutal from './uis/brutal3.js';
@@ -83,6 +94,7 @@ function App() {
     const [downloads, setDownloads] = useState([]);
     const [url, setUrl] = useState('');
     const [tab, setTab] = useState('downloads');
+    const [showCrawl, setShowCrawl] = useState(false);
     const [searchTerm, setSearchTerm] = useState('');
     const [speedLimit, setSpeedLimit] = useState(null);
 
@@ -153,8 +165,9 @@ function App() {
 
         try {
             const [exists, path] = await invoke('check_file_existence', { url });
+            const policy = await invoke('get_conflict_policy').catch(() => 'ask');
 
-            if (exists) {
+            if (exists && policy === 'ask') {
                 setFileConflict({ url, filePath: path });
                 setPromptDefault(path.split(/[\\/]/).pop());
                 return;
@@ -216,6 +229,19 @@ function App() {
     };
 
 
+    const refreshDownloadUrl = async (dl) => {
+        const url = window.prompt('Paste a fresh link for this download:', dl.url);
+        if (!url || url === dl.url) return;
+        try {
+            await invoke('refresh_download_url', { id: dl.id, url });
+            await refresh();
+        } catch (error) {
+            console.error('Failed to refresh download link:', error);
+            window.alert(`Could not use the new link: ${error}`);
+        }
+    };
+
+
     const handleRemoveClick = (dl) => {
         setDownloadToRemove(dl);
 
@@ -257,30 +283,43 @@ function App() {
 
 
     useEffect(() => {
-        const handleProgress = async () => {
-            try {
-                const dls = await invoke('get_downloads');
-                setDownloads(dls);
-
-
-                dls.forEach(async dl => {
-                    if (dl.status === 'completed') {
-                        const fileName = dl.file_path?.split(/[\\/]/).pop() ?? 'Unknown File';
-                        sendNotif("Download Complete", `${fileName} has finished downloading!`);
-                    } else if (typeof dl.status === 'string' && dl.status.startsWith('failed')) {
-                        const fileName = dl.file_path?.split(/[\\/]/).pop() ?? 'Unknown File';
-                        sendNotif("Download Failed", `${fileName} failed to download: ${dl.status.replace('failed: ', '')}`);
-                    }
-                });
-
-            } catch (err) {
-                console.error('Failed to get downloads:', err);
+        const upsert = (download) => setDownloads(prev => {
+            const index = prev.findIndex(d => d.id === download.id);
+            if (index === -1) return [...prev, download];
+            const next = [...prev];
+            next[index] = download;
+            return next;
+        });
+
+        const handleEvent = ({ payload: event }) => {
+            switch (event.type) {
+                case 'progress':
+                    setDownloads(prev => prev.map(d => d.id === event.id ? {
+                        ...d,
+                        progress: event.progress,
+                        downloaded_bytes: event.downloaded_bytes,
+                        total_bytes: event.total_bytes,
+                        speed_kbps: event.speed_kbps,
+                        eta_seconds: event.eta_seconds,
+                    } : d));
+                    break;
+                case 'removed':
+                    setDownloads(prev => prev.filter(d => d.id !== event.id));
+                    break;
+                case 'settings_changed':
+                    break;
+                case 'link_detected':
+                    setUrl(current => current.trim() ? current : event.url);
+                    break;
+                default:
+                    // Completion and failure notifications come from the backend.
+                    upsert(event.download);
             }
         };
 
         refresh();
 
-        const unlistenPromise = listen('download-progress', handleProgress);
+        const unlistenPromise = listen('download-event', handleEvent);
 
         return () => {
             unlistenPromise.then(unlisten => unlisten());
@@ -426,8 +465,16 @@ function App() {
                 >
                     Download
                 </button>
+                <button
+                    {...theme.getButtonStyles('resume', false)}
+                    onClick={() => setShowCrawl(!showCrawl)}
+                >
+                    Crawl
+                </button>
             </div>
 
+            {showCrawl && <SiteCrawl url={url} theme={theme} onClose={() => setShowCrawl(false)} />}
+
             <div style={theme.styles.tabRow}>
                 <button
                     {...theme.getButtonStyles('tab', false)}
@@ -466,6 +513,7 @@ function App() {
                                     const isQueued = queue.includes(dl.id);
                                     const isPauseDisabled = dl.status === 'paused' || dl.status === 'completed' || dl.status === 'failed';
                                     const isResumeDisabled = dl.status === 'downloading' || dl.status === 'completed' || dl.status === 'failed';
+                                    const canRefreshUrl = dl.status === 'paused' || dl.status === 'disk_full' || !!dl.status?.failed;
                                     const queuePosition = isQueued ? queue.indexOf(dl.id) + 1 : null;
                                     const extension = getFullExtension(dl.file_path);
                                     const isExpanded = expandedItems.has(dl.id);
@@ -497,6 +545,9 @@ function App() {
                                                 <>
                                                     <div style={theme.styles.expandedDetails}>
                                                         <div style={theme.styles.expandedDetailsText}><strong>STATUS:</strong> <span>{(dl.status?.failed ? dl.status.failed : dl.status.toUpperCase())}</span></div>
+                                                        {dl.status?.failed && dl.error && (
+                                                            <div style={theme.styles.expandedDetailsText}><strong>ERROR:</strong> {dl.error.kind === 'http' ? `HTTP ${dl.error.status}` : dl.error.kind.toUpperCase()}{!dl.error.retryable && ' (FATAL)'}</div>
+                                                        )}
                                                         <div style={theme.styles.expandedDetailsText}>
                                                             <strong>SPEED:</strong> {dl.speed_kbps ? dl.speed_kbps.toFixed(2) : '0.00'} KB/S
                                                             {speedLimit != 0 && (
@@ -508,6 +559,9 @@ function App() {
                                                         <div style={theme.styles.expandedDetailsText}><strong>ETA:</strong> {formatTime(dl.eta_seconds).toUpperCase()}</div>
                                                         <div style={theme.styles.expandedDetailsText}><strong>DOWNLOADED:</strong> {formatBytes(dl.downloaded_bytes).toUpperCase()}</div>
                                                         <div style={theme.styles.expandedDetailsText}><strong>TOTAL:</strong> {formatBytes(dl.total_bytes).toUpperCase()}</div>
+                                                        {dl.effective_url && dl.effective_url !== dl.url && (
+                                                            <div style={theme.styles.expandedDetailsText} title={dl.redirect_chain?.join('\n')}><strong>SERVED BY:</strong> {dl.effective_url}</div>
+                                                        )}
                                                     </div>
 
                                                     <div style={theme.styles.expandedActions}>
@@ -525,6 +579,14 @@ function App() {
                                                         >
                                                             RESUME
                                                         </button>
+                                                        {canRefreshUrl &&
+                                                            <button
+                                                                {...theme.getButtonStyles('resume', false)}
+                                                                onClick={(e) => { e.stopPropagation(); refreshDownloadUrl(dl); }}
+                                                            >
+                                                                NEW LINK
+                                                            </button>
+                                                        }
                                                         <button
                                                             {...theme.getButtonStyles('remove', false)}
                                                             onClick={(e) => { e.stopPropagation(); handleRemoveClick(dl); }}
@@ -595,6 +657,16 @@ function App() {
                             <MaxConcurrentDownloadsInput maxConcurrentDownloads={maxConcurrentDownloads} setMaxConcurrentDownloads={setMaxConcurrentDownloads} refresh={refresh} />
                             <DownloadPathInput downloadPath={downloadPath} setDownloadPath={setDownloadPath} refresh={refresh} />
                             <RetrySettings retryAttempts={retryAttempts} setRetryAttempts={setRetryAttempts} retryDelay={retryDelay} setRetryDelay={setRetryDelay} refresh={refresh} />
+                            <ConflictPolicySelect />
+                            <DiskSpaceSettings />
+                            <TimeoutSettings />
+                            <NotificationSettings />
+                            <ClipboardSettings />
+                            <ResolverSettings />
+                            <ScriptSettings />
+                            <FeedSettings />
+                            <WatchFolderSettings />
+                            <ImportExportSettings />
                             <AutoStartToggle autoStart={autoStart} setAutoStart={setAutoStart} refresh={refresh} />
                         </div>
                     </div>
@@ -0,0 +1,90 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+
+const inputStyle = {
+    padding: '0.5rem',
+    fontSize: '1rem',
+    border: '1px solid #ccc',
+    borderRadius: '4px',
+    width: '100%',
+    boxSizing: 'border-box',
+};
+
+const toList = (text) => text.split(',').map(s => s.trim()).filter(Boolean);
+
+const ClipboardSettings = () => {
+    const [settings, setSettings] = useState(null);
+    const [extensions, setExtensions] = useState('');
+    const [hosts, setHosts] = useState('');
+
+    useEffect(() => {
+        invoke('get_clipboard_settings')
+            .then(s => {
+                setSettings(s);
+                setExtensions(s.extensions.join(', '));
+                setHosts(s.hosts.join(', '));
+            })
+            .catch(console.error);
+    }, []);
+
+    const save = async (next) => {
+        setSettings(next);
+        await invoke('set_clipboard_settings', { settings: next });
+    };
+
+    if (!settings) return null;
+
+    return (
+        <div style={{ marginTop: '1rem' }}>
+            <label style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                Clipboard Monitor
+            </label>
+            <div style={{ display: 'flex', alignItems: 'center', gap: '0.5rem' }}>
+                <input
+                    type="checkbox"
+                    id="clipboard-enabled"
+                    checked={settings.enabled}
+                    onChange={() => save({ ...settings, enabled: !settings.enabled })}
+                />
+                <label htmlFor="clipboard-enabled" style={{ fontSize: '1rem' }}>
+                    Watch the clipboard for download links
+                </label>
+            </div>
+            <div style={{ display: 'flex', alignItems: 'center', gap: '0.5rem', paddingTop: '0.25rem' }}>
+                <input
+                    type="checkbox"
+                    id="clipboard-auto"
+                    checked={settings.auto_enqueue}
+                    onChange={() => save({ ...settings, auto_enqueue: !settings.auto_enqueue })}
+                />
+                <label htmlFor="clipboard-auto" style={{ fontSize: '1rem' }}>
+                    Add links straight to the queue
+                </label>
+            </div>
+            <label htmlFor="clipboard-extensions" style={{ display: 'block', paddingTop: '0.5rem' }}>
+                Extensions (comma separated)
+            </label>
+            <input
+                id="clipboard-extensions"
+                type="text"
+                value={extensions}
+                onChange={e => setExtensions(e.target.value)}
+                onBlur={() => save({ ...settings, extensions: toList(extensions) })}
+                style={inputStyle}
+            />
+            <label htmlFor="clipboard-hosts" style={{ display: 'block', paddingTop: '0.5rem' }}>
+                Hosts (comma separated)
+            </label>
+            <input
+                id="clipboard-hosts"
+                type="text"
+                value={hosts}
+                onChange={e => setHosts(e.target.value)}
+                onBlur={() => save({ ...settings, hosts: toList(hosts) })}
+                style={inputStyle}
+            />
+        </div>
+    );
+};
+
+export default ClipboardSettings;
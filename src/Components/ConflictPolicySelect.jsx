@@ -0,0 +1,45 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+
+const ConflictPolicySelect = () => {
+    const [policy, setPolicy] = useState('ask');
+
+    useEffect(() => {
+        invoke('get_conflict_policy')
+            .then(setPolicy)
+            .catch(console.error);
+    }, []);
+
+    const handleChange = async (e) => {
+        const value = e.target.value;
+        setPolicy(value);
+        await invoke('set_conflict_policy', { policy: value });
+    };
+
+    return (
+        <div style={{ marginTop: '1rem' }}>
+            <label htmlFor="conflict-policy" style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                When File Already Exists
+            </label>
+            <select
+                id="conflict-policy"
+                value={policy}
+                onChange={handleChange}
+                style={{
+                    padding: '0.5rem',
+                    fontSize: '1rem',
+                    border: '1px solid #ccc',
+                    borderRadius: '4px',
+                }}
+            >
+                <option value="ask">Ask</option>
+                <option value="rename">Rename automatically</option>
+                <option value="overwrite">Overwrite</option>
+                <option value="skip">Skip</option>
+                <option value="resume">Resume</option>
+            </select>
+        </div>
+    );
+};
+
+export default ConflictPolicySelect;
@@ -0,0 +1,65 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+
+const DiskSpaceSettings = () => {
+    const [minFreeMb, setMinFreeMb] = useState(200);
+    const [preallocate, setPreallocate] = useState(false);
+
+    useEffect(() => {
+        invoke('get_min_free_space_mb')
+            .then(setMinFreeMb)
+            .catch(console.error);
+        invoke('get_preallocate')
+            .then(setPreallocate)
+            .catch(console.error);
+    }, []);
+
+    const handleThresholdChange = async (e) => {
+        const val = parseInt(e.target.value, 10);
+        if (!isNaN(val) && val >= 0) {
+            setMinFreeMb(val);
+            await invoke('set_min_free_space_mb', { value: val });
+        }
+    };
+
+    const handlePreallocateToggle = async () => {
+        const next = !preallocate;
+        setPreallocate(next);
+        await invoke('set_preallocate', { enabled: next });
+    };
+
+    return (
+        <div style={{ marginTop: '1rem' }}>
+            <label htmlFor="min-free-space" style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                Pause Downloads Below Free Space (MB, 0 = off)
+            </label>
+            <input
+                id="min-free-space"
+                type="number"
+                min="0"
+                value={minFreeMb}
+                onChange={handleThresholdChange}
+                style={{
+                    padding: '0.5rem',
+                    fontSize: '1rem',
+                    border: '1px solid #ccc',
+                    borderRadius: '4px',
+                    width: '100px',
+                }}
+            />
+            <div style={{ display: 'flex', alignItems: 'center', gap: '0.5rem', paddingTop: '0.5rem' }}>
+                <input
+                    type="checkbox"
+                    id="preallocate"
+                    checked={preallocate}
+                    onChange={handlePreallocateToggle}
+                />
+                <label htmlFor="preallocate" style={{ fontSize: '1rem' }}>
+                    Preallocate disk space for downloads
+                </label>
+            </div>
+        </div>
+    );
+};
+
+export default DiskSpaceSettings;
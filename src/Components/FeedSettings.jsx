@@ -0,0 +1,178 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+const inputStyle = {
+    padding: '0.5rem',
+    fontSize: '1rem',
+    border: '1px solid #ccc',
+    borderRadius: '4px',
+    width: '100%',
+    boxSizing: 'border-box',
+};
+
+const buttonStyle = {
+    padding: '0.4rem 0.8rem',
+    fontSize: '0.9rem',
+    border: '1px solid #ccc',
+    borderRadius: '4px',
+    cursor: 'pointer',
+};
+
+const rowStyle = { display: 'flex', alignItems: 'center', gap: '0.5rem', paddingTop: '0.25rem' };
+
+const toList = (text) => text.split(',').map(s => s.trim()).filter(Boolean);
+
+const emptyFeed = { url: '', category: '', include: '', exclude: '', download_existing: false };
+
+const formatChecked = (secs) => secs ? new Date(secs * 1000).toLocaleString() : 'never';
+
+const FeedSettings = () => {
+    const [feeds, setFeeds] = useState([]);
+    const [interval, setInterval_] = useState(60);
+    const [draft, setDraft] = useState(emptyFeed);
+    const [error, setError] = useState(null);
+
+    const load = () => invoke('list_feeds').then(setFeeds).catch(console.error);
+
+    useEffect(() => {
+        load();
+        invoke('get_feed_interval')
+            .then(setInterval_)
+            .catch(console.error);
+
+        const unlistenPromise = listen('download-event', ({ payload: event }) => {
+            if (event.type === 'settings_changed' && event.key === 'feeds') load();
+        });
+        return () => {
+            unlistenPromise.then(unlisten => unlisten());
+        };
+    }, []);
+
+    const saveInterval = async () => {
+        await invoke('set_feed_interval', { minutes: Math.max(0, Number(interval) || 0) });
+    };
+
+    const addFeed = async () => {
+        setError(null);
+        try {
+            await invoke('add_feed', {
+                feed: {
+                    ...draft,
+                    url: draft.url.trim(),
+                    include: toList(draft.include),
+                    exclude: toList(draft.exclude),
+                },
+            });
+            setDraft(emptyFeed);
+        } catch (e) {
+            setError(String(e));
+        }
+    };
+
+    const updateFeed = async (feed) => {
+        setFeeds(prev => prev.map(f => f.id === feed.id ? feed : f));
+        await invoke('update_feed', { feed }).catch(e => setError(String(e)));
+    };
+
+    const checkFeed = (id) => invoke('check_feed', { id }).catch(e => setError(String(e)));
+
+    const removeFeed = (id) => invoke('remove_feed', { id }).catch(console.error);
+
+    return (
+        <div style={{ marginTop: '1rem' }}>
+            <label style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                Feed Subscriptions
+            </label>
+            <div style={rowStyle}>
+                <label htmlFor="feed-interval" style={{ fontSize: '1rem' }}>
+                    Check every (minutes, 0 = only by hand)
+                </label>
+                <input
+                    id="feed-interval"
+                    type="number"
+                    min={0}
+                    value={interval}
+                    onChange={e => setInterval_(e.target.value)}
+                    onBlur={saveInterval}
+                    style={{ ...inputStyle, width: '6rem' }}
+                />
+            </div>
+
+            {feeds.map(feed => (
+                <div key={feed.id} style={{ borderTop: '1px solid #ccc', marginTop: '0.5rem', paddingTop: '0.5rem' }}>
+                    <div style={rowStyle}>
+                        <input
+                            type="checkbox"
+                            id={`feed-${feed.id}`}
+                            checked={feed.enabled}
+                            onChange={() => updateFeed({ ...feed, enabled: !feed.enabled })}
+                        />
+                        <label htmlFor={`feed-${feed.id}`} style={{ fontSize: '1rem', flex: 1 }} title={feed.url}>
+                            {feed.title || feed.url}
+                        </label>
+                        <button style={buttonStyle} onClick={() => checkFeed(feed.id)}>Check Now</button>
+                        <button style={buttonStyle} onClick={() => removeFeed(feed.id)}>Remove</button>
+                    </div>
+                    <div style={{ fontSize: '0.85rem', paddingLeft: '1.5rem' }}>
+                        {feed.category ? `Category ${feed.category}, ` : ''}
+                        checked {formatChecked(feed.last_checked)}
+                        {feed.include.length > 0 && `, only "${feed.include.join('", "')}"`}
+                        {feed.exclude.length > 0 && `, not "${feed.exclude.join('", "')}"`}
+                    </div>
+                    {feed.last_error &&
+                        <div style={{ fontSize: '0.85rem', paddingLeft: '1.5rem', color: 'red' }}>{feed.last_error}</div>
+                    }
+                </div>
+            ))}
+
+            <div style={{ borderTop: '1px solid #ccc', marginTop: '0.5rem', paddingTop: '0.5rem' }}>
+                <input
+                    type="text"
+                    value={draft.url}
+                    onChange={e => setDraft({ ...draft, url: e.target.value })}
+                    placeholder="RSS or Atom feed URL"
+                    style={inputStyle}
+                />
+                <div style={rowStyle}>
+                    <input
+                        type="text"
+                        value={draft.category}
+                        onChange={e => setDraft({ ...draft, category: e.target.value })}
+                        placeholder="Category (optional)"
+                        style={inputStyle}
+                    />
+                    <input
+                        type="text"
+                        value={draft.include}
+                        onChange={e => setDraft({ ...draft, include: e.target.value })}
+                        placeholder="Title contains (comma separated, re: for regex)"
+                        style={inputStyle}
+                    />
+                    <input
+                        type="text"
+                        value={draft.exclude}
+                        onChange={e => setDraft({ ...draft, exclude: e.target.value })}
+                        placeholder="Skip titles containing"
+                        style={inputStyle}
+                    />
+                </div>
+                <div style={rowStyle}>
+                    <input
+                        type="checkbox"
+                        id="feed-existing"
+                        checked={draft.download_existing}
+                        onChange={() => setDraft({ ...draft, download_existing: !draft.download_existing })}
+                    />
+                    <label htmlFor="feed-existing" style={{ fontSize: '1rem', flex: 1 }}>
+                        Also download items already in the feed
+                    </label>
+                    <button style={buttonStyle} onClick={addFeed} disabled={!draft.url.trim()}>Subscribe</button>
+                </div>
+                {error && <p style={{ color: 'red', margin: '0.25rem 0' }}>{error}</p>}
+            </div>
+        </div>
+    );
+};
+
+export default FeedSettings;
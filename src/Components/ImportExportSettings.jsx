@@ -0,0 +1,118 @@
+import { useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { open, save } from '@tauri-apps/plugin-dialog';
+
+const buttonStyle = {
+    padding: '0.4rem 0.8rem',
+    fontSize: '0.9rem',
+    border: '1px solid #ccc',
+    borderRadius: '4px',
+    cursor: 'pointer',
+};
+
+const rowStyle = { display: 'flex', alignItems: 'center', gap: '0.5rem', paddingTop: '0.25rem', flexWrap: 'wrap' };
+
+const statuses = ['queued', 'paused', 'completed', 'failed'];
+
+const ImportExportSettings = () => {
+    const [selected, setSelected] = useState([]);
+    const [mode, setMode] = useState('merge');
+    const [message, setMessage] = useState(null);
+
+    const toggleStatus = (status) => setSelected(prev =>
+        prev.includes(status) ? prev.filter(s => s !== status) : [...prev, status]);
+
+    const run = async (action) => {
+        setMessage(null);
+        try {
+            const result = await action();
+            if (result) setMessage(result);
+        } catch (e) {
+            setMessage(String(e));
+        }
+    };
+
+    const exportDownloads = () => run(async () => {
+        const path = await save({
+            title: "Export downloads",
+            defaultPath: 'madbyte-downloads.json',
+            filters: [{ name: 'JSON', extensions: ['json'] }, { name: 'CSV', extensions: ['csv'] }],
+        });
+        if (!path) return null;
+        await invoke('export_downloads', { path, statuses: selected });
+        return `Exported to ${path}`;
+    });
+
+    const importDownloads = () => run(async () => {
+        const path = await open({
+            title: "Import downloads",
+            multiple: false,
+            filters: [{ name: 'Download lists', extensions: ['json', 'csv'] }],
+        });
+        if (typeof path !== 'string') return null;
+        const report = await invoke('import_downloads', { path, mode });
+        const errors = report.errors.length ? `, ${report.errors.length} failed` : '';
+        return `Imported ${report.imported}, skipped ${report.skipped}${errors}`;
+    });
+
+    const exportSettings = () => run(async () => {
+        const path = await save({
+            title: "Export settings",
+            defaultPath: 'madbyte-settings.json',
+            filters: [{ name: 'JSON', extensions: ['json'] }],
+        });
+        if (!path) return null;
+        await invoke('export_settings', { path });
+        return `Exported to ${path}`;
+    });
+
+    const importSettings = () => run(async () => {
+        const path = await open({
+            title: "Import settings",
+            multiple: false,
+            filters: [{ name: 'JSON', extensions: ['json'] }],
+        });
+        if (typeof path !== 'string') return null;
+        await invoke('import_settings', { path, mode });
+        // Every settings panel reads its values once, on mount.
+        window.location.reload();
+        return null;
+    });
+
+    return (
+        <div style={{ marginTop: '1rem' }}>
+            <label style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                Import / Export
+            </label>
+            <div style={rowStyle}>
+                <span>Export only:</span>
+                {statuses.map(status => (
+                    <label key={status} style={{ display: 'flex', alignItems: 'center', gap: '0.25rem' }}>
+                        <input
+                            type="checkbox"
+                            checked={selected.includes(status)}
+                            onChange={() => toggleStatus(status)}
+                        />
+                        {status}
+                    </label>
+                ))}
+            </div>
+            <div style={rowStyle}>
+                <label htmlFor="import-mode">On import</label>
+                <select id="import-mode" value={mode} onChange={e => setMode(e.target.value)}>
+                    <option value="merge">Merge with what is here</option>
+                    <option value="replace">Replace what is here</option>
+                </select>
+            </div>
+            <div style={rowStyle}>
+                <button style={buttonStyle} onClick={exportDownloads}>Export Downloads</button>
+                <button style={buttonStyle} onClick={importDownloads}>Import Downloads</button>
+                <button style={buttonStyle} onClick={exportSettings}>Export Settings</button>
+                <button style={buttonStyle} onClick={importSettings}>Import Settings</button>
+            </div>
+            {message && <p style={{ margin: '0.25rem 0' }}>{message}</p>}
+        </div>
+    );
+};
+
+export default ImportExportSettings;
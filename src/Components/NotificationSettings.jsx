@@ -0,0 +1,50 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+
+const toggles = [
+    ['on_completed', 'Notify when a download completes'],
+    ['on_failed', 'Notify when a download fails'],
+    ['on_queue_empty', 'Notify when the queue is empty'],
+    ['open_folder_on_click', 'Open the folder when a notification is clicked'],
+];
+
+const NotificationSettings = () => {
+    const [settings, setSettings] = useState(null);
+
+    useEffect(() => {
+        invoke('get_notification_settings')
+            .then(setSettings)
+            .catch(console.error);
+    }, []);
+
+    const handleToggle = (key) => async () => {
+        const next = { ...settings, [key]: !settings[key] };
+        setSettings(next);
+        await invoke('set_notification_settings', { settings: next });
+    };
+
+    if (!settings) return null;
+
+    return (
+        <div style={{ marginTop: '1rem' }}>
+            <label style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                Notifications
+            </label>
+            {toggles.map(([key, label]) => (
+                <div key={key} style={{ display: 'flex', alignItems: 'center', gap: '0.5rem', paddingTop: '0.25rem' }}>
+                    <input
+                        type="checkbox"
+                        id={`notify-${key}`}
+                        checked={settings[key]}
+                        onChange={handleToggle(key)}
+                    />
+                    <label htmlFor={`notify-${key}`} style={{ fontSize: '1rem' }}>
+                        {label}
+                    </label>
+                </div>
+            ))}
+        </div>
+    );
+};
+
+export default NotificationSettings;
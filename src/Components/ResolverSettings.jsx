@@ -0,0 +1,74 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+
+const labels = {
+    github_releases: 'GitHub release pages',
+    anchor_download: 'Download links on web pages (requests every link first)',
+};
+
+const ResolverSettings = () => {
+    const [settings, setSettings] = useState(null);
+    const [resolvers, setResolvers] = useState([]);
+
+    useEffect(() => {
+        invoke('get_resolver_settings')
+            .then(setSettings)
+            .catch(console.error);
+        invoke('list_resolvers')
+            .then(setResolvers)
+            .catch(console.error);
+    }, []);
+
+    const save = async (next) => {
+        setSettings(next);
+        await invoke('set_resolver_settings', { settings: next });
+    };
+
+    // Opt-in resolvers are listed in `opted_in`, the others in `disabled`.
+    const isOn = ({ name, opt_in }) =>
+        opt_in ? settings.opted_in.includes(name) : !settings.disabled.includes(name);
+
+    const toggleResolver = (resolver) => {
+        const key = resolver.opt_in ? 'opted_in' : 'disabled';
+        const names = settings[key].includes(resolver.name)
+            ? settings[key].filter(n => n !== resolver.name)
+            : [...settings[key], resolver.name];
+        save({ ...settings, [key]: names });
+    };
+
+    if (!settings) return null;
+
+    return (
+        <div style={{ marginTop: '1rem' }}>
+            <label style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                Link Resolvers
+            </label>
+            <div style={{ display: 'flex', alignItems: 'center', gap: '0.5rem' }}>
+                <input
+                    type="checkbox"
+                    id="resolvers-enabled"
+                    checked={settings.enabled}
+                    onChange={() => save({ ...settings, enabled: !settings.enabled })}
+                />
+                <label htmlFor="resolvers-enabled" style={{ fontSize: '1rem' }}>
+                    Find the files behind page links
+                </label>
+            </div>
+            {settings.enabled && resolvers.map(resolver => (
+                <div key={resolver.name} style={{ display: 'flex', alignItems: 'center', gap: '0.5rem', paddingTop: '0.25rem', paddingLeft: '1.5rem' }}>
+                    <input
+                        type="checkbox"
+                        id={`resolver-${resolver.name}`}
+                        checked={isOn(resolver)}
+                        onChange={() => toggleResolver(resolver)}
+                    />
+                    <label htmlFor={`resolver-${resolver.name}`} style={{ fontSize: '1rem' }}>
+                        {labels[resolver.name] ?? resolver.name}
+                    </label>
+                </div>
+            ))}
+        </div>
+    );
+};
+
+export default ResolverSettings;
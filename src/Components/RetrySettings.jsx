@@ -1,30 +1,78 @@
 import { invoke } from '@tauri-apps/api/core';
 import { useEffect, useState } from 'react';
 
+const inputStyle = { marginLeft: '10px', padding: '0.5rem', width: '5rem' };
+
 function RetrySettings() {
-    const [maxRetries, setMaxRetries] = useState(10);
+    const [policy, setPolicy] = useState(null);
 
     useEffect(() => {
-        invoke('get_max_retries').then(setMaxRetries);
+        invoke('get_retry_policy').then(setPolicy).catch(console.error);
     }, []);
 
-    const updateRetries = async (e) => {
-        const value = parseInt(e.target.value);
-        setMaxRetries(value);
-        await invoke('set_max_retries', { max: value });
+    const update = async (changes) => {
+        const next = { ...policy, ...changes };
+        setPolicy(next);
+        await invoke('set_retry_policy', { policy: next });
+    };
+
+    const updateNumber = (key, scale = 1) => (e) => {
+        const value = parseFloat(e.target.value);
+        if (!isNaN(value) && value >= 0) {
+            update({ [key]: Math.round(value * scale) });
+        }
     };
 
+    const updateStatuses = (e) => {
+        const statuses = e.target.value
+            .split(',')
+            .map((s) => parseInt(s.trim(), 10))
+            .filter((n) => !isNaN(n));
+        update({ retry_statuses: statuses });
+    };
+
+    if (!policy) return null;
+
     return (
         <div style={{ marginTop: '1rem' }}>
-            <label style={{ fontWeight: 'bold' }}>Max Retries</label>
-            <input
-                type="number"
-                value={maxRetries}
-                min={0}
-                max={20}
-                onChange={updateRetries}
-                style={{ marginLeft: '10px', padding: '0.5rem', width: '5rem' }}
-            />
+            <div>
+                <label style={{ fontWeight: 'bold' }}>Max Retries</label>
+                <input
+                    type="number"
+                    value={policy.max_attempts}
+                    min={0}
+                    max={20}
+                    onChange={updateNumber('max_attempts')}
+                    style={inputStyle}
+                />
+            </div>
+            <div style={{ marginTop: '0.5rem' }}>
+                <label>Retry delay (s)</label>
+                <input
+                    type="number"
+                    value={policy.base_delay_ms / 1000}
+                    min={0}
+                    onChange={updateNumber('base_delay_ms', 1000)}
+                    style={inputStyle}
+                />
+                <label style={{ marginLeft: '10px' }}>up to (s)</label>
+                <input
+                    type="number"
+                    value={policy.max_delay_ms / 1000}
+                    min={0}
+                    onChange={updateNumber('max_delay_ms', 1000)}
+                    style={inputStyle}
+                />
+            </div>
+            <div style={{ marginTop: '0.5rem' }}>
+                <label>Retry on HTTP status</label>
+                <input
+                    type="text"
+                    defaultValue={policy.retry_statuses.join(', ')}
+                    onBlur={updateStatuses}
+                    style={{ ...inputStyle, width: '14rem' }}
+                />
+            </div>
         </div>
     );
 }
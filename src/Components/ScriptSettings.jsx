@@ -0,0 +1,77 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+
+const buttonStyle = {
+    padding: '0.4rem 0.8rem',
+    fontSize: '0.9rem',
+    border: '1px solid #ccc',
+    borderRadius: '4px',
+    cursor: 'pointer',
+    marginRight: '0.5rem',
+};
+
+const ScriptSettings = () => {
+    const [settings, setSettings] = useState(null);
+    const [scripts, setScripts] = useState([]);
+    const [dir, setDir] = useState('');
+
+    useEffect(() => {
+        invoke('get_script_settings')
+            .then(setSettings)
+            .catch(console.error);
+        invoke('list_scripts')
+            .then(setScripts)
+            .catch(console.error);
+        invoke('get_scripts_dir')
+            .then(setDir)
+            .catch(console.error);
+    }, []);
+
+    const handleToggle = async () => {
+        const next = { ...settings, enabled: !settings.enabled };
+        setSettings(next);
+        await invoke('set_script_settings', { settings: next });
+    };
+
+    const handleReload = async () => {
+        try {
+            setScripts(await invoke('reload_scripts'));
+        } catch (e) {
+            console.error("Failed to reload scripts:", e);
+        }
+    };
+
+    if (!settings) return null;
+
+    return (
+        <div style={{ marginTop: '1rem' }}>
+            <label style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                Scripts
+            </label>
+            <div style={{ display: 'flex', alignItems: 'center', gap: '0.5rem' }}>
+                <input
+                    type="checkbox"
+                    id="scripts-enabled"
+                    checked={settings.enabled}
+                    onChange={handleToggle}
+                />
+                <label htmlFor="scripts-enabled" style={{ fontSize: '1rem' }}>
+                    Run user scripts (.rhai) on download events
+                </label>
+            </div>
+            <div style={{ paddingTop: '0.5rem' }}>
+                <button style={buttonStyle} title={dir} onClick={() => invoke('open_scripts_dir').catch(console.error)}>Open Folder</button>
+                <button style={buttonStyle} onClick={handleReload}>Reload</button>
+            </div>
+            {scripts.map(script => (
+                <div key={script.name} style={{ fontSize: '0.9rem', paddingTop: '0.25rem' }}>
+                    {script.name}: {script.error
+                        ? <span style={{ color: 'red' }}>{script.error}</span>
+                        : (script.hooks.join(', ') || 'no hooks')}
+                </div>
+            ))}
+        </div>
+    );
+};
+
+export default ScriptSettings;
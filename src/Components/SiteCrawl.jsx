@@ -0,0 +1,130 @@
+import { useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+
+const lines = (text) => text.split('\n').map(l => l.trim()).filter(Boolean);
+
+const fieldStyle = { display: 'flex', alignItems: 'center', gap: '0.5rem', paddingTop: '0.25rem' };
+
+const SiteCrawl = ({ url, theme, onClose }) => {
+    const [depth, setDepth] = useState(3);
+    const [noParent, setNoParent] = useState(true);
+    const [hosts, setHosts] = useState('');
+    const [include, setInclude] = useState('');
+    const [exclude, setExclude] = useState('');
+    const [files, setFiles] = useState(null);
+    const [busy, setBusy] = useState(false);
+    const [error, setError] = useState(null);
+
+    const options = () => ({
+        depth: Number(depth) || 0,
+        no_parent: noParent,
+        hosts: lines(hosts),
+        include: lines(include),
+        exclude: lines(exclude),
+    });
+
+    const preview = async () => {
+        setBusy(true);
+        setError(null);
+        try {
+            setFiles(await invoke('crawl_url', { url: url.trim(), options: options() }));
+        } catch (e) {
+            setError(String(e));
+            setFiles(null);
+        } finally {
+            setBusy(false);
+        }
+    };
+
+    const downloadAll = async () => {
+        setBusy(true);
+        setError(null);
+        try {
+            await invoke('enqueue_crawled', { files });
+            setFiles(null);
+            onClose();
+        } catch (e) {
+            setError(String(e));
+        } finally {
+            setBusy(false);
+        }
+    };
+
+    return (
+        <div style={{ marginBottom: '1rem' }}>
+            <label style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                Crawl Site
+            </label>
+            <div style={fieldStyle}>
+                <label htmlFor="crawl-depth" style={{ fontSize: '1rem' }}>Depth</label>
+                <input
+                    type="number"
+                    id="crawl-depth"
+                    min={0}
+                    value={depth}
+                    onChange={(e) => setDepth(e.target.value)}
+                    style={{ width: '4rem' }}
+                />
+                <input
+                    type="checkbox"
+                    id="crawl-no-parent"
+                    checked={noParent}
+                    onChange={() => setNoParent(!noParent)}
+                />
+                <label htmlFor="crawl-no-parent" style={{ fontSize: '1rem' }}>
+                    Stay below this folder
+                </label>
+            </div>
+            <div style={fieldStyle}>
+                <textarea
+                    value={include}
+                    onChange={(e) => setInclude(e.target.value)}
+                    placeholder={'Include, one per line (*.iso, re:\\.tar\\.gz$)'}
+                    rows={2}
+                    style={{ flex: 1 }}
+                />
+                <textarea
+                    value={exclude}
+                    onChange={(e) => setExclude(e.target.value)}
+                    placeholder={'Exclude, one per line (old/**, *.sig)'}
+                    rows={2}
+                    style={{ flex: 1 }}
+                />
+                <textarea
+                    value={hosts}
+                    onChange={(e) => setHosts(e.target.value)}
+                    placeholder={'Other hosts to follow, one per line'}
+                    rows={2}
+                    style={{ flex: 1 }}
+                />
+            </div>
+            <div style={fieldStyle}>
+                <button
+                    {...theme.getButtonStyles('resume', busy || !url.trim())}
+                    onClick={preview}
+                    disabled={busy || !url.trim()}
+                >
+                    {busy && !files ? 'Crawling...' : 'Preview'}
+                </button>
+                {files && files.length > 0 &&
+                    <button
+                        {...theme.getButtonStyles('primary', busy)}
+                        onClick={downloadAll}
+                        disabled={busy}
+                    >
+                        Download {files.length} files
+                    </button>
+                }
+            </div>
+            {error && <p style={{ color: 'red', margin: '0.25rem 0' }}>{error}</p>}
+            {files && files.length === 0 && <p style={{ margin: '0.25rem 0' }}>No files found.</p>}
+            {files && files.length > 0 && (
+                <ul style={{ maxHeight: '10rem', overflowY: 'auto', margin: '0.25rem 0', fontSize: '0.85rem' }}>
+                    {files.map(f => <li key={f.url} title={f.url}>{f.path}</li>)}
+                </ul>
+            )}
+        </div>
+    );
+};
+
+export default SiteCrawl;
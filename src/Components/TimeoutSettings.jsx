@@ -0,0 +1,61 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+
+const inputStyle = {
+    padding: '0.5rem',
+    fontSize: '1rem',
+    border: '1px solid #ccc',
+    borderRadius: '4px',
+    width: '80px',
+    marginLeft: '10px',
+};
+
+const fields = [
+    ['connect_secs', 'Connect timeout (s)'],
+    ['read_idle_secs', 'No data timeout (s)'],
+    ['stall_min_kbps', 'Stalled below (KB/s)'],
+    ['stall_secs', 'for (s)'],
+];
+
+const TimeoutSettings = () => {
+    const [timeouts, setTimeouts] = useState(null);
+
+    useEffect(() => {
+        invoke('get_timeouts')
+            .then(setTimeouts)
+            .catch(console.error);
+    }, []);
+
+    const handleChange = (key) => async (e) => {
+        const val = key === 'stall_min_kbps' ? parseFloat(e.target.value) : parseInt(e.target.value, 10);
+        if (!isNaN(val) && val >= 0) {
+            const next = { ...timeouts, [key]: val };
+            setTimeouts(next);
+            await invoke('set_timeouts', { timeouts: next });
+        }
+    };
+
+    if (!timeouts) return null;
+
+    return (
+        <div style={{ marginTop: '1rem' }}>
+            <label style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                Timeouts (0 = off)
+            </label>
+            {fields.map(([key, label]) => (
+                <div key={key} style={{ paddingTop: '0.25rem' }}>
+                    <label>{label}</label>
+                    <input
+                        type="number"
+                        min="0"
+                        value={timeouts[key]}
+                        onChange={handleChange(key)}
+                        style={inputStyle}
+                    />
+                </div>
+            ))}
+        </div>
+    );
+};
+
+export default TimeoutSettings;
@@ -0,0 +1,88 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { open } from '@tauri-apps/plugin-dialog';
+
+const inputStyle = {
+    padding: '0.5rem',
+    fontSize: '1rem',
+    border: '1px solid #ccc',
+    borderRadius: '4px',
+    boxSizing: 'border-box',
+};
+
+const buttonStyle = {
+    padding: '0.4rem 0.8rem',
+    fontSize: '0.9rem',
+    border: '1px solid #ccc',
+    borderRadius: '4px',
+    cursor: 'pointer',
+};
+
+const rowStyle = { display: 'flex', alignItems: 'center', gap: '0.5rem', paddingTop: '0.25rem' };
+
+const WatchFolderSettings = () => {
+    const [folders, setFolders] = useState([]);
+    const [error, setError] = useState(null);
+
+    useEffect(() => {
+        invoke('get_watch_folders')
+            .then(setFolders)
+            .catch(console.error);
+    }, []);
+
+    const save = async (next) => {
+        setFolders(next);
+        setError(null);
+        await invoke('set_watch_folders', { folders: next }).catch(e => setError(String(e)));
+    };
+
+    const update = (index, changes) => save(folders.map((f, i) => i === index ? { ...f, ...changes } : f));
+
+    const addFolder = async () => {
+        const selected = await open({
+            directory: true,
+            multiple: false,
+            title: "Choose a folder to watch:"
+        });
+        if (typeof selected === 'string' && !folders.some(f => f.path === selected)) {
+            save([...folders, { path: selected, enabled: true, category: '' }]);
+        }
+    };
+
+    return (
+        <div style={{ marginTop: '1rem' }}>
+            <label style={{ display: 'block', fontWeight: 'bold', marginBottom: '0.5rem' }}>
+                Watch Folders
+            </label>
+            <p style={{ fontSize: '0.85rem', margin: '0 0 0.25rem' }}>
+                URL lists (.txt), .torrent web seeds, .metalink/.meta4 and .json jobs dropped here are queued, then moved to processed/ or failed/.
+            </p>
+            {folders.map((folder, index) => (
+                <div key={folder.path} style={rowStyle}>
+                    <input
+                        type="checkbox"
+                        checked={folder.enabled}
+                        onChange={() => update(index, { enabled: !folder.enabled })}
+                    />
+                    <span style={{ flex: 1, overflow: 'hidden', textOverflow: 'ellipsis' }} title={folder.path}>
+                        {folder.path}
+                    </span>
+                    <input
+                        type="text"
+                        defaultValue={folder.category}
+                        onBlur={e => e.target.value !== folder.category && update(index, { category: e.target.value.trim() })}
+                        placeholder="Category"
+                        style={{ ...inputStyle, width: '8rem' }}
+                    />
+                    <button style={buttonStyle} onClick={() => save(folders.filter((_, i) => i !== index))}>Remove</button>
+                </div>
+            ))}
+            <div style={rowStyle}>
+                <button style={buttonStyle} onClick={addFolder}>Add Folder</button>
+            </div>
+            {error && <p style={{ color: 'red', margin: '0.25rem 0' }}>{error}</p>}
+        </div>
+    );
+};
+
+export default WatchFolderSettings;